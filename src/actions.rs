@@ -4,9 +4,14 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex, Notify};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::Stream;
 
-use crate::handlers::Handler;
+use crate::client::KernelControl;
+use crate::handlers::dispatch::Dispatcher;
+use crate::handlers::{Handler, HandlerContext, HandlerFailure, HandlerSet};
+use crate::heartbeat::Liveness;
 use crate::jupyter::iopub_content::status::KernelStatus;
 use crate::jupyter::request::Request;
 use crate::jupyter::response::Response;
@@ -23,6 +28,7 @@ impl From<&Request> for ExpectedReplyType {
         match request {
             Request::KernelInfo(_) => ExpectedReplyType::KernelInfo,
             Request::Execute(_) => ExpectedReplyType::ExecuteReply,
+            Request::Interrupt(_) | Request::Shutdown(_) => ExpectedReplyType::None,
         }
     }
 }
@@ -37,46 +43,130 @@ impl From<&Response> for ExpectedReplyType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionStatus {
+    // Kernel went idle and the expected reply was seen
+    Completed,
+    // The heartbeat monitor declared the Kernel Dead before the Action completed
+    KernelDied,
+    // Action::cancel was called, or every Client clone was dropped, before the Action completed
+    Cancelled,
+    // A FallibleHandler with ErrorPolicy::Abort failed
+    Aborted,
+}
+
+// What an awaited Action resolves to
+#[derive(Debug, Clone)]
+pub struct ActionOutcome {
+    pub status: ActionStatus,
+    // Everything FallibleHandlers returned Err for, in the order it happened
+    pub errors: Vec<HandlerFailure>,
+}
+
+impl ActionOutcome {
+    pub fn is_completed(&self) -> bool {
+        self.status == ActionStatus::Completed
+    }
+}
+
+// So callers that don't care about errors can compare against a status directly
+impl PartialEq<ActionStatus> for ActionOutcome {
+    fn eq(&self, status: &ActionStatus) -> bool {
+        self.status == *status
+    }
+}
+
 #[derive(Debug)]
 struct ActionState {
-    completed: bool,
+    outcome: Option<ActionOutcome>,
     waker: Option<Waker>,
 }
 
+impl ActionState {
+    // The first outcome sticks, e.g. a cancelled Action stays Cancelled if the Kernel dies after
+    fn complete(&mut self, status: ActionStatus, errors: Vec<HandlerFailure>) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.outcome = Some(ActionOutcome { status, errors });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+// Everything the listener needs to carry out Action::cancel, or stop when the Action is dropped
+#[derive(Debug)]
+struct Cancellation {
+    requested: Arc<Notify>,
+    dropped: Arc<Notify>,
+    msg_id: String,
+    control: Arc<KernelControl>,
+}
+
+// Dropping an Action before it completes stops its Handlers and the routing of its responses,
+// without interrupting the Kernel. Await it (or keep it around) for Handlers to see everything.
 #[derive(Debug)]
 pub struct Action {
     pub request: Request,
     state: Arc<Mutex<ActionState>>,
+    cancel: Arc<Notify>,
+    dropped: Arc<Notify>,
 }
 
 impl Action {
-    pub fn new(
+    pub(crate) fn new(
         request: Request,
-        handlers: Vec<Arc<Mutex<dyn Handler>>>,
+        handlers: HandlerSet,
         msg_rx: mpsc::Receiver<Response>,
+        liveness: watch::Receiver<Liveness>,
+        control: Arc<KernelControl>,
+        context: HandlerContext,
     ) -> Self {
         let action_state = Arc::new(Mutex::new(ActionState {
-            completed: false,
+            outcome: None,
             waker: None,
         }));
         let expected_reply = ExpectedReplyType::from(&request);
+        let cancel = Arc::new(Notify::new());
+        let dropped = Arc::new(Notify::new());
+        let cancellation = Cancellation {
+            requested: cancel.clone(),
+            dropped: dropped.clone(),
+            msg_id: request.msg_id(),
+            control,
+        };
         // spawn background task for listening
         tokio::spawn(Action::listen(
             msg_rx,
             expected_reply,
-            handlers,
+            Dispatcher::new(handlers, context),
+            liveness,
+            cancellation,
             action_state.clone(),
         ));
         Action {
             request,
             state: action_state,
+            cancel,
+            dropped,
         }
     }
 
+    // Stop the request and resolve the Action with ActionStatus::Cancelled. Handlers don't see any
+    // more messages. If the Kernel is running the request, it gets interrupted. Kernels have no way
+    // to drop a request that's still queued, so a queued one is interrupted as soon as the Kernel
+    // starts it instead. Does nothing if the Action already completed.
+    pub fn cancel(&self) {
+        self.cancel.notify_one();
+    }
+
     async fn listen(
         mut msg_rx: mpsc::Receiver<Response>,
         expected_reply: ExpectedReplyType,
-        handlers: Vec<Arc<Mutex<dyn Handler>>>,
+        mut dispatcher: Dispatcher,
+        mut liveness: watch::Receiver<Liveness>,
+        cancellation: Cancellation,
         action_state: Arc<Mutex<ActionState>>,
     ) {
         // We "finish" this background task when kernel idle and expected reply (if relevant) seen
@@ -86,10 +176,42 @@ impl Action {
             ExpectedReplyType::ExecuteReply => false,
             ExpectedReplyType::None => true,
         };
-        while let Some(response) = msg_rx.recv().await {
-            for handler_arc in &handlers {
-                let mut handler = handler_arc.lock().await;
-                handler.handle(&response).await;
+        let mut cancelled = false;
+        // None when there's nobody left to tell
+        let outcome = loop {
+            if *liveness.borrow_and_update() == Liveness::Dead {
+                break Some((ActionStatus::KernelDied, dispatcher.finish().await.1));
+            }
+            let response = tokio::select! {
+                response = msg_rx.recv() => match response {
+                    Some(response) => response,
+                    None => break None,
+                },
+                changed = liveness.changed() => {
+                    if changed.is_ok() {
+                        continue;
+                    }
+                    // Every Client clone was dropped, which shuts the connection down and closes
+                    // liveness. Nothing more will come in for this request.
+                    break Some((ActionStatus::Cancelled, dispatcher.detach()));
+                }
+                _ = cancellation.requested.notified() => {
+                    cancelled = true;
+                    // Detach handlers, dropping them also ends any ResponseStream
+                    break Some((ActionStatus::Cancelled, dispatcher.detach()));
+                }
+                // Nobody is left to see the outcome, just stop
+                _ = cancellation.dropped.notified() => {
+                    dispatcher.detach();
+                    break None;
+                }
+                // Only happens with DispatchMode::Concurrent, sequential Handlers abort below
+                _ = dispatcher.aborted() => {
+                    break Some((ActionStatus::Aborted, dispatcher.detach()));
+                }
+            };
+            if !dispatcher.dispatch(&response).await {
+                break Some((ActionStatus::Aborted, dispatcher.detach()));
             }
             match response {
                 Response::Status(status) => {
@@ -104,19 +226,37 @@ impl Action {
                 }
             }
             if kernel_idle && expected_reply_seen {
-                let mut state = action_state.lock().await;
-                state.completed = true;
-                if let Some(waker) = state.waker.take() {
-                    waker.wake();
-                }
-                break;
+                // A concurrent Handler may abort while working through what's still queued
+                let (aborted, errors) = dispatcher.finish().await;
+                let status = if aborted {
+                    ActionStatus::Aborted
+                } else {
+                    ActionStatus::Completed
+                };
+                break Some((status, errors));
             }
+        };
+        // Whichever way it ended, nothing more should be routed here. Done before resolving the
+        // Action so whoever awaits it sees the request cleaned up.
+        cancellation.control.detach(&cancellation.msg_id).await;
+        if cancelled {
+            cancellation.control.cancel(&cancellation.msg_id).await;
+        }
+        if let Some((status, errors)) = outcome {
+            action_state.lock().await.complete(status, errors);
         }
     }
 }
 
+impl Drop for Action {
+    fn drop(&mut self) {
+        // Harmless if the listener already finished
+        self.dropped.notify_one();
+    }
+}
+
 impl Future for Action {
-    type Output = ();
+    type Output = ActionOutcome;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut state = match self.state.try_lock() {
@@ -127,11 +267,72 @@ impl Future for Action {
                 return Poll::Pending;
             }
         };
-        if state.completed {
-            Poll::Ready(())
+        if let Some(outcome) = &state.outcome {
+            Poll::Ready(outcome.clone())
         } else {
             state.waker = Some(cx.waker().clone());
             Poll::Pending
         }
     }
 }
+
+// Forwards every Response into a channel for ResponseStream. The Action's listener drops its
+// handlers once it finishes, which closes the channel and ends the stream.
+#[derive(Debug)]
+struct ForwardHandler {
+    tx: mpsc::UnboundedSender<Response>,
+}
+
+#[async_trait::async_trait]
+impl Handler for ForwardHandler {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
+        // Nobody listening anymore if the ResponseStream was dropped, that's fine
+        let _ = self.tx.send(msg.clone());
+    }
+}
+
+// Responses for a request as a Stream instead of Handlers. Ends when the Action completes (or is
+// cancelled, or the Kernel dies).
+//
+// let mut stream = client.execute_request_stream("2 + 2".to_string()).await;
+// while let Some(msg) = stream.next().await {
+//     dbg!(msg);
+// }
+#[derive(Debug)]
+pub struct ResponseStream {
+    action: Action,
+    responses: UnboundedReceiverStream<Response>,
+}
+
+impl ResponseStream {
+    // Wraps an Action-building function so its responses come out of the stream
+    pub(crate) async fn new<F, Fut>(send: F) -> Self
+    where
+        F: FnOnce(HandlerSet) -> Fut,
+        Fut: Future<Output = Action>,
+    {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let action = send(HandlerSet::new().with(ForwardHandler { tx })).await;
+        Self {
+            action,
+            responses: UnboundedReceiverStream::new(rx),
+        }
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    // How the Action finished, resolves once the stream has ended
+    pub async fn outcome(self) -> ActionOutcome {
+        self.action.await
+    }
+}
+
+impl Stream for ResponseStream {
+    type Item = Response;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.responses).poll_next(cx)
+    }
+}
@@ -24,32 +24,39 @@ struct DebugHandler;
 
 #[async_trait::async_trait]
 impl Handler for DebugHandler {
-    async fn handle(&self, msg: &Response) {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
         dbg!(msg);
     }
 }
 
 let handler = DebugHandler {};
-let handlers = vec![Arc::new(handler) as Arc<dyn Handler>];
+let handlers = vec![Arc::new(Mutex::new(handler)) as Arc<Mutex<dyn Handler>>];
 let action = client.kernel_info_request(handlers).await;
 action.await;
 */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, Mutex, Notify, RwLock};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Notify, RwLock};
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
-use zeromq::{DealerSocket, ReqSocket, Socket, SocketRecv, SocketSend, SubSocket, ZmqMessage};
+use zeromq::{DealerSocket, Socket, SocketRecv, SocketSend, SubSocket, ZmqMessage};
 
-use crate::actions::Action;
-use crate::handlers::Handler;
+use crate::actions::{Action, ActionStatus, ResponseStream};
+use crate::execution_queue::{ExecutionQueue, QueueMode};
+use crate::handlers::{HandlerContext, HandlerSet};
+use crate::heartbeat::{self, HeartbeatConfig, HeartbeatTimeout, Liveness};
 use crate::jupyter::connection_file::ConnectionInfo;
+use crate::jupyter::control_content::interrupt::InterruptRequest;
+use crate::jupyter::control_content::shutdown::ShutdownRequest;
+use crate::jupyter::iopub_content::status::KernelStatus;
 use crate::jupyter::request::Request;
 use crate::jupyter::response::Response;
 use crate::jupyter::shell_content::execute::ExecuteRequest;
-use crate::jupyter::shell_content::kernel_info::KernelInfoRequest;
+use crate::jupyter::shell_content::kernel_info::{KernelInfoReply, KernelInfoRequest};
 use crate::jupyter::wire_protocol::WireProtocol;
 
 #[derive(Debug, Clone)]
@@ -57,7 +64,265 @@ pub struct Client {
     actions: Arc<RwLock<HashMap<String, mpsc::Sender<Response>>>>,
     connection_info: ConnectionInfo,
     shell_tx: mpsc::Sender<ZmqMessage>,
-    shutdown_signal: Arc<Notify>,
+    control: Arc<KernelControl>,
+    activity: Arc<std::sync::Mutex<KernelActivity>>,
+    // Most recent kernel_info_reply, whichever request it was for
+    kernel_info: Arc<std::sync::Mutex<Option<KernelInfoReply>>>,
+    kernel_state: watch::Sender<KernelState>,
+    shutdown: Arc<ShutdownOnDrop>,
+}
+
+// Why Client::wait_until_ready gave up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadyError {
+    // No heartbeat or completed kernel_info_request within the timeout
+    Timeout(Duration),
+    // The heartbeat monitor declared the Kernel Dead while waiting
+    KernelDied,
+}
+
+impl fmt::Display for ReadyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadyError::Timeout(timeout) => {
+                write!(f, "Kernel was not ready within {:?}", timeout)
+            }
+            ReadyError::KernelDied => write!(f, "Kernel died before it was ready"),
+        }
+    }
+}
+
+impl std::error::Error for ReadyError {}
+
+// A Client handle that doesn't keep the Kernel connection open, see Client::downgrade
+#[derive(Debug, Clone)]
+pub struct WeakClient {
+    actions: Arc<RwLock<HashMap<String, mpsc::Sender<Response>>>>,
+    connection_info: ConnectionInfo,
+    shell_tx: mpsc::Sender<ZmqMessage>,
+    control: Arc<KernelControl>,
+    activity: Arc<std::sync::Mutex<KernelActivity>>,
+    kernel_info: Arc<std::sync::Mutex<Option<KernelInfoReply>>>,
+    kernel_state: watch::Sender<KernelState>,
+    shutdown: Weak<ShutdownOnDrop>,
+}
+
+impl WeakClient {
+    // None once every Client clone has been dropped and the connection is shut down
+    pub fn upgrade(&self) -> Option<Client> {
+        Some(Client {
+            actions: self.actions.clone(),
+            connection_info: self.connection_info.clone(),
+            shell_tx: self.shell_tx.clone(),
+            control: self.control.clone(),
+            activity: self.activity.clone(),
+            kernel_info: self.kernel_info.clone(),
+            kernel_state: self.kernel_state.clone(),
+            shutdown: self.shutdown.upgrade()?,
+        })
+    }
+}
+
+// Bookkeeping about what the Kernel has been up to, regardless of which request it was for. Used by
+// KernelManager to find idle Kernels to cull. Busy / idle status is tracked in KernelState.
+#[derive(Debug, Clone)]
+pub struct KernelActivity {
+    pub last_request: Option<Instant>,
+    // comm_id's of comms the Kernel has opened and not closed yet (widgets and the like)
+    pub open_comms: HashSet<String>,
+    connected: Instant,
+}
+
+impl KernelActivity {
+    fn new() -> Self {
+        Self {
+            last_request: None,
+            open_comms: HashSet::new(),
+            connected: Instant::now(),
+        }
+    }
+
+    fn observe(&mut self, response: &Response) {
+        if let Response::Unmodeled(msg) = response {
+            let comm_id = msg.content.0.get("comm_id").and_then(|id| id.as_str());
+            match (msg.header.msg_type.as_str(), comm_id) {
+                ("comm_open", Some(comm_id)) => {
+                    self.open_comms.insert(comm_id.to_string());
+                }
+                ("comm_close", Some(comm_id)) => {
+                    self.open_comms.remove(comm_id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // How long it's been since the Kernel last went idle or was sent a request, whichever is more
+    // recent. Zero while the Kernel is busy. See Client::idle_for.
+    pub fn idle_for(&self, state: &KernelState) -> Duration {
+        let last_idle = match state.status {
+            Some(KernelStatus::Busy) => return Duration::ZERO,
+            Some(KernelStatus::Idle) => Some(state.since),
+            _ => None,
+        };
+        let last_active = [self.last_request, last_idle]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(self.connected);
+        last_active.elapsed()
+    }
+}
+
+// Latest execution_state from any status message, no matter which request (or other client) it was
+// for. See Client::kernel_state.
+#[derive(Debug, Clone)]
+pub struct KernelState {
+    // None until the first status message arrives
+    pub status: Option<KernelStatus>,
+    // When status last changed
+    pub since: Instant,
+    // Time spent busy, not counting the current busy stretch, see total_busy_time
+    pub busy_time: Duration,
+}
+
+impl KernelState {
+    fn new() -> Self {
+        Self {
+            status: None,
+            since: Instant::now(),
+            busy_time: Duration::ZERO,
+        }
+    }
+
+    // Returns whether the status changed
+    fn observe(&mut self, status: &KernelStatus) -> bool {
+        if self.status.as_ref() == Some(status) {
+            return false;
+        }
+        let now = Instant::now();
+        if self.status == Some(KernelStatus::Busy) {
+            self.busy_time += now - self.since;
+        }
+        self.status = Some(status.clone());
+        self.since = now;
+        true
+    }
+
+    // Time spent busy including the current busy stretch, if any
+    pub fn total_busy_time(&self) -> Duration {
+        match self.status {
+            Some(KernelStatus::Busy) => self.busy_time + self.since.elapsed(),
+            _ => self.busy_time,
+        }
+    }
+}
+
+// The parts of a Client that Actions need for cancelling, without keeping the whole Client alive
+#[derive(Debug)]
+pub(crate) struct KernelControl {
+    actions: Arc<RwLock<HashMap<String, mpsc::Sender<Response>>>>,
+    control_tx: mpsc::Sender<ZmqMessage>,
+    key: String,
+    execution: std::sync::Mutex<Execution>,
+}
+
+#[derive(Debug, Default)]
+struct Execution {
+    // msg_id of the request the Kernel is running, from the parent of its latest busy status
+    executing: Option<String>,
+    // Cancelled requests the Kernel hadn't started yet, interrupted once it does
+    cancelled: HashSet<String>,
+}
+
+impl KernelControl {
+    // Interrupt a cancelled request: right away if the Kernel is running it, otherwise as soon as
+    // it starts. Kernels have no way to drop a request that's still queued.
+    pub(crate) async fn cancel(&self, msg_id: &str) {
+        let executing = {
+            let mut execution = self.execution.lock().unwrap();
+            let executing = execution.executing.as_deref() == Some(msg_id);
+            if !executing {
+                execution.cancelled.insert(msg_id.to_string());
+            }
+            executing
+        };
+        if executing {
+            self.interrupt().await;
+        }
+    }
+
+    // Keep track of what the Kernel is running, interrupting requests that were cancelled before
+    // they started. Called before the status reaches the Action, so a cancel handled after that
+    // sees the request as running.
+    async fn observe_status(&self, state: &KernelStatus, parent_msg_id: Option<String>) {
+        let interrupt = {
+            let mut execution = self.execution.lock().unwrap();
+            match state {
+                KernelStatus::Busy => {
+                    let cancelled = parent_msg_id
+                        .as_ref()
+                        .is_some_and(|msg_id| execution.cancelled.remove(msg_id));
+                    execution.executing = parent_msg_id;
+                    cancelled
+                }
+                KernelStatus::Idle => {
+                    if let Some(msg_id) = &parent_msg_id {
+                        execution.cancelled.remove(msg_id);
+                    }
+                    if execution.executing == parent_msg_id {
+                        execution.executing = None;
+                    }
+                    false
+                }
+                _ => false,
+            }
+        };
+        if interrupt {
+            self.interrupt().await;
+        }
+    }
+
+    // Send an interrupt_request over the control channel
+    pub(crate) async fn interrupt(&self) {
+        self.send(InterruptRequest::new().into()).await;
+    }
+
+    // Send a shutdown_request over the control channel
+    pub(crate) async fn shutdown(&self) {
+        self.send(ShutdownRequest::new(false).into()).await;
+    }
+
+    async fn send(&self, request: Request) {
+        let wp: WireProtocol = request.into_wire_protocol(&self.key);
+        // The control worker is gone once the Client is dropped, nothing left to send for
+        let _ = self.control_tx.send(wp.into()).await;
+    }
+
+    // Stop routing responses for a request
+    pub(crate) async fn detach(&self, msg_id: &str) {
+        self.actions.write().await.remove(msg_id);
+    }
+}
+
+// Client is Clone, so the background ZMQ tasks should only be shut down once the last clone is
+// dropped. Every clone shares one of these and the notify happens when it's dropped. The liveness
+// sender lives here too, so pending Actions and liveness() subscribers see it close along with the
+// Client.
+#[derive(Debug)]
+struct ShutdownOnDrop {
+    signal: Arc<Notify>,
+    liveness: watch::Sender<Liveness>,
+    heartbeat_monitor: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for ShutdownOnDrop {
+    fn drop(&mut self) {
+        self.signal.notify_waiters();
+        if let Some(task) = self.heartbeat_monitor.lock().unwrap().take() {
+            task.abort();
+        }
+    }
 }
 
 impl Client {
@@ -65,6 +330,8 @@ impl Client {
         let actions = Arc::new(RwLock::new(HashMap::new()));
         // message passing for methods to send requests out over shell channel via shell_worker
         let (shell_tx, shell_rx) = mpsc::channel(100);
+        // and interrupt_requests over control, which skip the line of queued shell requests
+        let (control_tx, control_rx) = mpsc::channel(100);
 
         // message passing for iopub and shell listeners into process_message_worker
         let (process_msg_tx, process_msg_rx) = mpsc::channel(100);
@@ -72,6 +339,16 @@ impl Client {
         // For shutting down ZMQ listeners when Client is dropped
         let shutdown_signal = Arc::new(Notify::new());
 
+        let activity = Arc::new(std::sync::Mutex::new(KernelActivity::new()));
+        let kernel_info = Arc::new(std::sync::Mutex::new(None));
+        let (kernel_state, _) = watch::channel(KernelState::new());
+        let control = Arc::new(KernelControl {
+            actions: actions.clone(),
+            control_tx,
+            key: connection_info.key.clone(),
+            execution: std::sync::Mutex::new(Execution::default()),
+        });
+
         // spawn iopub and shell listeners
         let iopub_address = connection_info.iopub_address();
         let shell_address = connection_info.shell_address();
@@ -87,11 +364,21 @@ impl Client {
             process_msg_tx.clone(),
             shutdown_signal.clone(),
         ));
+        tokio::spawn(shell_worker(
+            connection_info.control_address(),
+            control_rx,
+            process_msg_tx.clone(),
+            shutdown_signal.clone(),
+        ));
 
         // spawn process_message_worker
         tokio::spawn(process_message_worker(
             process_msg_rx,
             actions.clone(),
+            activity.clone(),
+            kernel_info.clone(),
+            kernel_state.clone(),
+            control.clone(),
             shutdown_signal.clone(),
         ));
 
@@ -99,81 +386,211 @@ impl Client {
             actions,
             connection_info,
             shell_tx,
-            shutdown_signal,
+            control,
+            activity,
+            kernel_info,
+            kernel_state,
+            shutdown: Arc::new(ShutdownOnDrop {
+                signal: shutdown_signal,
+                liveness: watch::channel(Liveness::Alive).0,
+                heartbeat_monitor: std::sync::Mutex::new(None),
+            }),
         }
     }
 
-    // Try to connect to the heartbeat channel and send a ping message
-    // You can use this as a way to wait for a new Kernel to come up or check if it's connected
+    // A handle that doesn't count towards handle_count or keep the Kernel connection open
+    pub fn downgrade(&self) -> WeakClient {
+        WeakClient {
+            actions: self.actions.clone(),
+            connection_info: self.connection_info.clone(),
+            shell_tx: self.shell_tx.clone(),
+            control: self.control.clone(),
+            activity: self.activity.clone(),
+            kernel_info: self.kernel_info.clone(),
+            kernel_state: self.kernel_state.clone(),
+            shutdown: Arc::downgrade(&self.shutdown),
+        }
+    }
+
+    pub fn activity(&self) -> KernelActivity {
+        self.activity.lock().unwrap().clone()
+    }
+
+    // See KernelActivity::idle_for
+    pub fn idle_for(&self) -> Duration {
+        self.activity().idle_for(&self.kernel_state.borrow())
+    }
+
+    // Requests whose responses are still being routed to an Action. Entries go away when the
+    // Action completes, is cancelled or is dropped.
+    pub async fn pending_actions(&self) -> usize {
+        self.actions.read().await.len()
+    }
+
+    // Number of live clones of this Client, including this one
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.shutdown)
+    }
+
+    // Ping the heartbeat channel until the Kernel answers. This waits forever if the Kernel never
+    // comes up, see wait_for_ready for a bounded version.
     pub async fn heartbeat(&self) {
+        let address = self.connection_info.heartbeat_address();
+        while !heartbeat::ping(&address, Duration::from_secs(1)).await {
+            sleep(Duration::from_millis(50)).await;
+        }
+        heartbeat::publish(&self.shutdown.liveness, Liveness::Alive);
+    }
+
+    // Ping the heartbeat channel until the Kernel answers or the timeout runs out
+    pub async fn wait_for_ready(&self, timeout: Duration) -> Result<(), HeartbeatTimeout> {
+        let deadline = Instant::now() + timeout;
+        let address = self.connection_info.heartbeat_address();
         loop {
-            let mut socket = ReqSocket::new();
-
-            // Try to connect to the heartbeat channel
-            if let Err(_e) = socket
-                .connect(self.connection_info.heartbeat_address().as_str())
-                .await
-            {
-                sleep(Duration::from_millis(50)).await;
-                continue; // If connection fails, retry in the next iteration of the loop
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(HeartbeatTimeout(timeout));
             }
-
-            // Send a ping message
-            let ping_msg = ZmqMessage::from("ping");
-            if let Err(_e) = socket.send(ping_msg).await {
-                sleep(Duration::from_millis(50)).await;
-                continue; // If sending fails, retry in the next iteration of the loop
+            if heartbeat::ping(&address, remaining.min(Duration::from_secs(1))).await {
+                heartbeat::publish(&self.shutdown.liveness, Liveness::Alive);
+                return Ok(());
             }
+            sleep(remaining.min(Duration::from_millis(50))).await;
+        }
+    }
 
-            // Wait for a pong message
-            match socket.recv().await {
-                Ok(_) => {
-                    break; // Successful pong message received, break the loop
-                }
-                Err(_) => {
-                    sleep(Duration::from_millis(50)).await;
-                    continue; // If receiving fails, retry in the next iteration of the loop
-                }
-            }
+    // Keep pinging the Kernel in the background, publishing Liveness changes to liveness()
+    // subscribers and pending Actions. Starting a monitor again replaces the previous one, and it
+    // stops when the last clone of this Client is dropped.
+    pub fn start_heartbeat_monitor(&self, config: HeartbeatConfig) {
+        let task = tokio::spawn(heartbeat::monitor_worker(
+            self.connection_info.heartbeat_address(),
+            config,
+            self.shutdown.liveness.clone(),
+        ));
+        let mut monitor = self.shutdown.heartbeat_monitor.lock().unwrap();
+        if let Some(previous) = monitor.replace(task) {
+            previous.abort();
         }
     }
 
+    // Subscribe to execution_state changes across every request sent to the Kernel
+    pub fn kernel_state(&self) -> watch::Receiver<KernelState> {
+        self.kernel_state.subscribe()
+    }
+
+    // Subscribe to Liveness changes. Without a running heartbeat monitor this stays Alive.
+    pub fn liveness(&self) -> watch::Receiver<Liveness> {
+        self.shutdown.liveness.subscribe()
+    }
+
     // Creates an Action from a request + handlers, serializes the request to be sent over ZMQ,
     // sends over shell channel, and registers the request header msg_id in the Actions hashmap
     // so that all response messages can get routed to the appropriate Action handlers
-    async fn send_request(
-        &self,
-        request: Request,
-        handlers: Vec<Arc<Mutex<dyn Handler>>>,
-    ) -> Action {
+    pub(crate) async fn send_request(&self, request: Request, handlers: HandlerSet) -> Action {
         let (msg_tx, msg_rx) = mpsc::channel(100);
-        let action = Action::new(request, handlers, msg_rx);
+        let context = HandlerContext::new(request.clone(), self);
+        let action = Action::new(
+            request,
+            handlers,
+            msg_rx,
+            self.shutdown.liveness.subscribe(),
+            self.control.clone(),
+            context,
+        );
         let msg_id = action.request.msg_id();
         self.actions.write().await.insert(msg_id.clone(), msg_tx);
+        self.activity.lock().unwrap().last_request = Some(Instant::now());
         let wp: WireProtocol = action.request.into_wire_protocol(&self.connection_info.key);
         let zmq_msg: ZmqMessage = wp.into();
         self.shell_tx.send(zmq_msg).await.unwrap();
         action
     }
 
-    pub async fn kernel_info_request(&self, handlers: Vec<Arc<Mutex<dyn Handler>>>) -> Action {
-        let request = KernelInfoRequest::new();
-        self.send_request(request.into(), handlers).await
+    // Wait until both shell and iopub are connected. A heartbeat only proves the Kernel is up, and
+    // a SUB socket can miss messages published before its subscription reaches the Kernel (ZMQ's
+    // slow joiner problem). So keep sending kernel_info_requests until one completes, since that
+    // means its iopub status messages made it back. Each attempt gets twice as long as the last
+    // before the next request goes out. Gives up once the timeout runs out, or when a heartbeat
+    // monitor declares the Kernel Dead.
+    pub async fn wait_until_ready(&self, timeout: Duration) -> Result<KernelInfoReply, ReadyError> {
+        let deadline = Instant::now() + timeout;
+        let mut liveness = self.liveness();
+        tokio::select! {
+            ready = self.wait_for_ready(timeout) => {
+                ready.map_err(|_| ReadyError::Timeout(timeout))?;
+            }
+            _ = liveness.wait_for(|liveness| *liveness == Liveness::Dead) => {
+                return Err(ReadyError::KernelDied);
+            }
+        }
+        let mut attempt_timeout = Duration::from_millis(100);
+        loop {
+            if *liveness.borrow() == Liveness::Dead {
+                return Err(ReadyError::KernelDied);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(ReadyError::Timeout(timeout));
+            }
+            let mut action = self.kernel_info_request(vec![]).await;
+            // Dropping an abandoned Action stops routing to it
+            match tokio::time::timeout(attempt_timeout.min(remaining), &mut action).await {
+                Ok(outcome) if outcome.status == ActionStatus::KernelDied => {
+                    return Err(ReadyError::KernelDied)
+                }
+                Ok(outcome) if outcome.is_completed() => {
+                    if let Some(reply) = self.kernel_info() {
+                        return Ok(reply);
+                    }
+                }
+                _ => {}
+            }
+            attempt_timeout = (attempt_timeout * 2).min(Duration::from_secs(2));
+        }
     }
 
-    pub async fn execute_request(
-        &self,
-        code: String,
-        handlers: Vec<Arc<Mutex<dyn Handler>>>,
-    ) -> Action {
-        let request = ExecuteRequest::new(code);
-        self.send_request(request.into(), handlers).await
+    // The last kernel_info_reply seen by this Client, None until a kernel_info_request completes.
+    // Use wait_until_ready to make sure there is one.
+    pub fn kernel_info(&self) -> Option<KernelInfoReply> {
+        self.kernel_info.lock().unwrap().clone()
     }
-}
 
-impl Drop for Client {
-    fn drop(&mut self) {
-        self.shutdown_signal.notify_waiters();
+    // Same as kernel_info_request, but responses come out of a Stream instead of going to Handlers
+    pub async fn kernel_info_request_stream(&self) -> ResponseStream {
+        ResponseStream::new(|handlers| self.kernel_info_request(handlers)).await
+    }
+
+    // Same as execute_request, but responses come out of a Stream instead of going to Handlers
+    pub async fn execute_request_stream(&self, code: String) -> ResponseStream {
+        ResponseStream::new(|handlers| self.execute_request(code, handlers)).await
+    }
+
+    // Run several cells in order and get an ok / error / aborted result for each, see ExecutionQueue
+    pub fn execution_queue(&self, mode: QueueMode) -> ExecutionQueue {
+        ExecutionQueue::new(self.clone(), mode)
+    }
+
+    // Interrupt whatever the Kernel is running with an interrupt_request on the control channel. To
+    // stop one particular request, Action::cancel is more precise.
+    pub async fn interrupt(&self) {
+        self.control.interrupt().await;
+    }
+
+    // Ask the Kernel to exit with a shutdown_request on the control channel. This doesn't wait for
+    // the process to go, and the Client is useless afterwards.
+    pub async fn shutdown_kernel(&self) {
+        self.control.shutdown().await;
+    }
+
+    pub async fn kernel_info_request(&self, handlers: impl Into<HandlerSet>) -> Action {
+        let request = KernelInfoRequest::new();
+        self.send_request(request.into(), handlers.into()).await
+    }
+
+    pub async fn execute_request(&self, code: String, handlers: impl Into<HandlerSet>) -> Action {
+        let request = ExecuteRequest::new(code);
+        self.send_request(request.into(), handlers.into()).await
     }
 }
 
@@ -183,19 +600,36 @@ impl Drop for Client {
 async fn process_message_worker(
     mut msg_rx: mpsc::Receiver<ZmqMessage>,
     actions: Arc<RwLock<HashMap<String, mpsc::Sender<Response>>>>,
+    activity: Arc<std::sync::Mutex<KernelActivity>>,
+    kernel_info: Arc<std::sync::Mutex<Option<KernelInfoReply>>>,
+    kernel_state: watch::Sender<KernelState>,
+    control: Arc<KernelControl>,
     shutdown_signal: Arc<Notify>, // hook to shutdown background task if Client is dropped
 ) {
     loop {
         tokio::select! {
             Some(zmq_msg) = msg_rx.recv() => {
                 let response: Response = zmq_msg.into();
+                activity.lock().unwrap().observe(&response);
+                match &response {
+                    Response::KernelInfo(msg) => {
+                        *kernel_info.lock().unwrap() = Some(msg.content.clone());
+                    }
+                    Response::Status(msg) => {
+                        kernel_state.send_if_modified(|state| state.observe(&msg.content.execution_state));
+                        control.observe_status(&msg.content.execution_state, msg.parent_msg_id()).await;
+                    }
+                    _ => {}
+                }
                 let msg_id = response.parent_msg_id();
                 if msg_id.is_none() {
                     dbg!("No parent msg id, skipping msg_type {}", response.msg_type());
                     continue;
                 }
                 let msg_id = msg_id.unwrap();
-                if let Some(action) = actions.read().await.get(&msg_id) {
+                // Not holding the lock while sending, the Action may be waiting on it to detach
+                let action = actions.read().await.get(&msg_id).cloned();
+                if let Some(action) = action {
                    let sent = action.send(response).await;
                    // If we're seeing SendError here, it means we're still seeing ZMQ messages with
                    // parent header msg id matching a request / Action that is "completed" and has
@@ -243,7 +677,7 @@ async fn iopub_worker(
 
 /// shell channel background task needs to have a way for the Client to send stuff out over shell
 /// in addition to listening for replies coming back on the channel, then pushing those to the
-/// process_message_worker.
+/// process_message_worker. The control channel works the same way and runs one of these too.
 async fn shell_worker(
     shell_address: String,
     mut msg_rx: mpsc::Receiver<ZmqMessage>, // Client wants to send Jupyter message over ZMQ
@@ -0,0 +1,280 @@
+/*
+ExecutionQueue sends a batch of cells to the Kernel in order and reports how each one went.
+
+let results = client
+    .execution_queue(QueueMode::HaltOnError)
+    .push("x = 1", vec![])
+    .push("1 / 0", vec![])
+    .push("x + 1", vec![])
+    .run()
+    .await;
+// [Ok, Error, Aborted]
+
+With Continue every execute_request is sent up front so the Kernel runs them back to back, and
+every cell runs regardless of earlier errors. With HaltOnError each cell is only sent once the one
+before it replied ok, and the cells after a failing one are reported as Aborted without being sent.
+That doesn't depend on the Kernel honoring stop_on_error, which evcxr, IRkernel and deno ignore.
+
+Cells pushed with push_cancellable can be cancelled while the queue runs. One that hasn't been sent
+yet is dropped, one that has is cancelled like Action::cancel. Either way it's reported as
+Cancelled and doesn't halt the queue.
+*/
+use std::sync::Arc;
+
+use tokio::sync::{watch, Mutex};
+
+use crate::actions::{Action, ActionStatus};
+use crate::client::Client;
+use crate::handlers::{Handler, HandlerContext, HandlerSet};
+use crate::jupyter::response::Response;
+use crate::jupyter::shell_content::execute::{ExecuteReply, ExecuteRequest, ExecuteStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueMode {
+    HaltOnError,
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionResult {
+    Ok {
+        execution_count: Option<u32>,
+    },
+    Error {
+        execution_count: Option<u32>,
+        ename: String,
+        evalue: String,
+        traceback: Vec<String>,
+    },
+    // The Kernel skipped this cell because an earlier one failed, or HaltOnError never sent it
+    Aborted,
+    // A FallibleHandler for this cell failed with ErrorPolicy::Abort, one "<msg_type>: <error>" per
+    // failure. The cell may still have run to completion in the Kernel.
+    HandlerAborted {
+        errors: Vec<String>,
+    },
+    // The cell's Action was cancelled before it completed
+    Cancelled,
+    // No execute_reply, the heartbeat monitor declared the Kernel Dead first
+    KernelDied,
+}
+
+impl From<ExecuteReply> for ExecutionResult {
+    fn from(reply: ExecuteReply) -> Self {
+        match reply.status {
+            ExecuteStatus::Ok => ExecutionResult::Ok {
+                execution_count: reply.execution_count,
+            },
+            ExecuteStatus::Error => ExecutionResult::Error {
+                execution_count: reply.execution_count,
+                ename: reply.ename.unwrap_or_default(),
+                evalue: reply.evalue.unwrap_or_default(),
+                traceback: reply.traceback,
+            },
+            ExecuteStatus::Aborted => ExecutionResult::Aborted,
+            // Whatever it means, the cell didn't succeed, so HaltOnError should stop at it
+            ExecuteStatus::Unknown(status) => ExecutionResult::Error {
+                execution_count: reply.execution_count,
+                ename: reply
+                    .ename
+                    .unwrap_or_else(|| format!("Unknown execute_reply status {}", status)),
+                evalue: reply.evalue.unwrap_or_default(),
+                traceback: reply.traceback,
+            },
+        }
+    }
+}
+
+// Holds on to the execute_reply for each queued cell
+#[derive(Debug)]
+struct ReplyCapture {
+    reply: Option<ExecuteReply>,
+}
+
+#[async_trait::async_trait]
+impl Handler for ReplyCapture {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
+        if let Response::Execute(msg) = msg {
+            self.reply = Some(msg.content.clone());
+        }
+    }
+}
+
+// Cancels a cell queued with ExecutionQueue::push_cancellable
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    cancelled: Arc<watch::Sender<bool>>,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            cancelled: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    async fn cancelled(&self) {
+        // The Sender lives as long as self, so this can't fail
+        let _ = self
+            .cancelled
+            .subscribe()
+            .wait_for(|cancelled| *cancelled)
+            .await;
+    }
+}
+
+#[derive(Debug)]
+struct QueueItem {
+    code: String,
+    handlers: HandlerSet,
+    cancel: Option<CancelToken>,
+}
+
+#[derive(Debug)]
+pub struct ExecutionQueue {
+    client: Client,
+    mode: QueueMode,
+    items: Vec<QueueItem>,
+}
+
+impl ExecutionQueue {
+    pub fn new(client: Client, mode: QueueMode) -> Self {
+        Self {
+            client,
+            mode,
+            items: vec![],
+        }
+    }
+
+    // Queue a cell, handlers see every response for it just like with Client::execute_request
+    pub fn push(mut self, code: &str, handlers: impl Into<HandlerSet>) -> Self {
+        self.items.push(QueueItem {
+            code: code.to_string(),
+            handlers: handlers.into(),
+            cancel: None,
+        });
+        self
+    }
+
+    // Like push, with a token for cancelling the cell while the queue runs
+    pub fn push_cancellable(
+        mut self,
+        code: &str,
+        handlers: impl Into<HandlerSet>,
+        cancel: &CancelToken,
+    ) -> Self {
+        self.items.push(QueueItem {
+            code: code.to_string(),
+            handlers: handlers.into(),
+            cancel: Some(cancel.clone()),
+        });
+        self
+    }
+
+    // Run the queued cells and wait for all of them, results are in the order cells were pushed
+    pub async fn run(self) -> Vec<ExecutionResult> {
+        let mut results = vec![];
+        match self.mode {
+            QueueMode::Continue => {
+                let mut pending = vec![];
+                for item in self.items {
+                    pending.push(send(&self.client, item, false).await);
+                }
+                for sent in pending {
+                    results.push(match sent {
+                        Some(sent) => sent.result().await,
+                        None => ExecutionResult::Cancelled,
+                    });
+                }
+            }
+            QueueMode::HaltOnError => {
+                let mut halted = false;
+                for item in self.items {
+                    if halted {
+                        results.push(ExecutionResult::Aborted);
+                        continue;
+                    }
+                    let result = match send(&self.client, item, true).await {
+                        Some(sent) => sent.result().await,
+                        None => ExecutionResult::Cancelled,
+                    };
+                    halted = !matches!(
+                        result,
+                        ExecutionResult::Ok { .. } | ExecutionResult::Cancelled
+                    );
+                    results.push(result);
+                }
+            }
+        }
+        results
+    }
+}
+
+// A queued cell that's been sent to the Kernel
+struct Sent {
+    action: Action,
+    capture: Arc<Mutex<ReplyCapture>>,
+    cancel: Option<CancelToken>,
+}
+
+impl Sent {
+    async fn result(mut self) -> ExecutionResult {
+        if let Some(cancel) = &self.cancel {
+            tokio::select! {
+                _ = &mut self.action => {}
+                _ = cancel.cancelled() => self.action.cancel(),
+            }
+        }
+        let outcome = (&mut self.action).await;
+        match outcome.status {
+            ActionStatus::Completed => self
+                .capture
+                .lock()
+                .await
+                .reply
+                .take()
+                .expect("Completed execute_request Actions have seen their execute_reply")
+                .into(),
+            ActionStatus::Aborted => ExecutionResult::HandlerAborted {
+                errors: outcome
+                    .errors
+                    .iter()
+                    .map(|failure| format!("{}: {}", failure.msg_type, failure.error))
+                    .collect(),
+            },
+            ActionStatus::Cancelled => ExecutionResult::Cancelled,
+            ActionStatus::KernelDied => ExecutionResult::KernelDied,
+        }
+    }
+}
+
+// None if the cell was cancelled before it could be sent
+async fn send(client: &Client, item: QueueItem, stop_on_error: bool) -> Option<Sent> {
+    if item.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+        return None;
+    }
+    let capture = Arc::new(Mutex::new(ReplyCapture { reply: None }));
+    let mut handlers = item.handlers;
+    handlers.push(capture.clone());
+    let request = ExecuteRequest::new(item.code).stop_on_error(stop_on_error);
+    let action = client.send_request(request.into(), handlers).await;
+    Some(Sent {
+        action,
+        capture,
+        cancel: item.cancel,
+    })
+}
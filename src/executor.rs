@@ -0,0 +1,358 @@
+/*
+Headless notebook execution, along the lines of papermill or `jupyter nbconvert --execute`. Meant
+for running .ipynb files in CI.
+
+let report = NotebookExecutor::new()
+    .cell_timeout(Duration::from_secs(600))
+    .execute_file("analysis.ipynb", "analysis.executed.ipynb")
+    .await?;
+for cell in &report.cells {
+    println!("{} {:?} {:?}", cell.cell_id, cell.status, cell.duration);
+}
+assert!(report.is_success());
+
+The Kernel comes from the notebook's metadata.kernelspec.name unless kernel_name is set. Code cells
+run one at a time, top to bottom. Execution stops at the first error unless allow_errors is set or
+the cell is tagged "raises-exception", and at the first cell that times out (the Kernel is
+interrupted) or if the Kernel dies. Cells after that are reported as Skipped. The Kernel is shut
+down once the run is over.
+*/
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::client::ReadyError;
+use crate::execution_queue::ExecutionResult;
+use crate::jupyter::kernelspec::KernelSpec;
+use crate::kernels::manager::{shutdown_gracefully, start_connected, while_alive};
+use crate::kernels::{KernelDied, KernelLaunchOptions, KernelManagerError};
+use crate::notebook::{Cell, Notebook};
+use crate::session::NotebookSession;
+
+#[derive(Debug)]
+pub enum ExecutorError {
+    // No kernel_name was given and the notebook has no metadata.kernelspec.name
+    NoKernelSpec,
+    SpecNotFound(String),
+    KernelDied(KernelDied),
+    // The Kernel started but didn't answer in time
+    NotReady(ReadyError),
+    // Reading the input notebook or writing the executed one, see execute_file
+    Io(std::io::Error),
+    // The input file isn't a valid notebook
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutorError::NoKernelSpec => {
+                write!(
+                    f,
+                    "Notebook has no metadata.kernelspec.name, set a kernel_name"
+                )
+            }
+            ExecutorError::SpecNotFound(name) => write!(f, "No kernelspec named {}", name),
+            ExecutorError::KernelDied(died) => write!(f, "{}", died),
+            ExecutorError::NotReady(error) => write!(f, "{}", error),
+            ExecutorError::Io(error) => write!(f, "{}", error),
+            ExecutorError::Parse(error) => write!(f, "Invalid notebook: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ExecutorError {}
+
+impl From<KernelDied> for ExecutorError {
+    fn from(died: KernelDied) -> Self {
+        ExecutorError::KernelDied(died)
+    }
+}
+
+impl From<std::io::Error> for ExecutorError {
+    fn from(error: std::io::Error) -> Self {
+        ExecutorError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ExecutorError {
+    fn from(error: serde_json::Error) -> Self {
+        ExecutorError::Parse(error)
+    }
+}
+
+// How long a timed out cell gets to wind down after the interrupt, so its outputs are all in
+// before the notebook is copied
+const INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellStatus {
+    Ok,
+    Error { ename: String, evalue: String },
+    // Errored, but it's tagged "raises-exception" so that was expected
+    ExpectedError { ename: String, evalue: String },
+    // Ran past cell_timeout and was interrupted
+    TimedOut,
+    // A Handler recording the cell's outputs failed, see ExecutionResult::HandlerAborted
+    HandlerAborted { errors: Vec<String> },
+    KernelDied,
+    // Not run because execution stopped at an earlier cell
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellReport {
+    pub cell_id: String,
+    pub status: CellStatus,
+    pub execution_count: Option<u32>,
+    // Zero for skipped cells
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    pub kernel_name: String,
+    // One per code cell, in notebook order
+    pub cells: Vec<CellReport>,
+    pub duration: Duration,
+    // Whether errors were allowed for this run, see NotebookExecutor::allow_errors
+    pub allow_errors: bool,
+}
+
+impl ExecutionReport {
+    // Every cell ran, and any errors were expected or allowed
+    pub fn is_success(&self) -> bool {
+        self.cells.iter().all(|cell| match cell.status {
+            CellStatus::Ok | CellStatus::ExpectedError { .. } => true,
+            CellStatus::Error { .. } => self.allow_errors,
+            CellStatus::TimedOut
+            | CellStatus::HandlerAborted { .. }
+            | CellStatus::KernelDied
+            | CellStatus::Skipped => false,
+        })
+    }
+
+    // The cell execution stopped at, if it stopped early
+    pub fn failed_cell(&self) -> Option<&CellReport> {
+        self.cells.iter().find(|cell| match cell.status {
+            CellStatus::Error { .. } => !self.allow_errors,
+            CellStatus::TimedOut | CellStatus::HandlerAborted { .. } | CellStatus::KernelDied => {
+                true
+            }
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NotebookExecutor {
+    kernel_name: Option<String>,
+    cell_timeout: Option<Duration>,
+    allow_errors: bool,
+    record_timing: bool,
+    options: KernelLaunchOptions,
+}
+
+impl Default for NotebookExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotebookExecutor {
+    pub fn new() -> Self {
+        Self {
+            kernel_name: None,
+            cell_timeout: None,
+            allow_errors: false,
+            record_timing: false,
+            options: KernelLaunchOptions::new().silent(true),
+        }
+    }
+
+    // Use this kernelspec instead of the one in the notebook metadata
+    pub fn kernel_name(mut self, kernel_name: &str) -> Self {
+        self.kernel_name = Some(kernel_name.to_string());
+        self
+    }
+
+    // How long a single cell may run, no limit by default
+    pub fn cell_timeout(mut self, timeout: Duration) -> Self {
+        self.cell_timeout = Some(timeout);
+        self
+    }
+
+    // Keep going after cells that error, like nbconvert --allow-errors
+    pub fn allow_errors(mut self, allow_errors: bool) -> Self {
+        self.allow_errors = allow_errors;
+        self
+    }
+
+    // Write JupyterLab-style metadata.execution timestamps into each cell
+    pub fn with_timing(mut self) -> Self {
+        self.record_timing = true;
+        self
+    }
+
+    // How the Kernel process is started. Kernel output is silent by default.
+    pub fn options(mut self, options: KernelLaunchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Run a notebook file and write the executed notebook to output_path, even when execution
+    // stopped early. The Kernel runs in the input notebook's directory unless options set a cwd.
+    pub async fn execute_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<ExecutionReport, ExecutorError> {
+        let input_path = input_path.as_ref();
+        let nb: Notebook = serde_json::from_str(&std::fs::read_to_string(input_path)?)?;
+        let mut executor = self.clone();
+        if executor.options.cwd.is_none() {
+            if let Some(dir) = input_path.parent().filter(|dir| dir.is_dir()) {
+                executor.options = executor.options.cwd(dir);
+            }
+        }
+        let (nb, report) = executor.execute(nb).await?;
+        std::fs::write(output_path, nb.dumps())?;
+        Ok(report)
+    }
+
+    // Run every code cell of an in-memory notebook, returns the executed notebook
+    pub async fn execute(
+        &self,
+        nb: Notebook,
+    ) -> Result<(Notebook, ExecutionReport), ExecutorError> {
+        let started = Instant::now();
+        let kernel_name = match &self.kernel_name {
+            Some(kernel_name) => kernel_name.clone(),
+            None => nb.metadata["kernelspec"]["name"]
+                .as_str()
+                .ok_or(ExecutorError::NoKernelSpec)?
+                .to_string(),
+        };
+        let spec = KernelSpec::find(&kernel_name)
+            .ok_or_else(|| ExecutorError::SpecNotFound(kernel_name.clone()))?;
+        let (kernel, client) = match start_connected(&spec, self.options.clone()).await {
+            Ok(started) => started,
+            Err(KernelManagerError::KernelDied(died)) => return Err(died.into()),
+            Err(KernelManagerError::NotReady(error)) => return Err(ExecutorError::NotReady(error)),
+            Err(error) => unreachable!("Starting a Kernel can't fail with {}", error),
+        };
+
+        let code_cells: Vec<(String, bool)> = nb
+            .cells
+            .iter()
+            .filter_map(|cell| match cell {
+                Cell::Code(code) => Some((code.id.clone(), raises_exception(cell))),
+                _ => None,
+            })
+            .collect();
+        let nb = Arc::new(Mutex::new(nb));
+        let mut session = NotebookSession::new(nb.clone(), client.clone());
+        if self.record_timing {
+            session = session.with_timing();
+        }
+
+        let mut cells = vec![];
+        let mut stopped = false;
+        for (cell_id, raises_exception) in code_cells {
+            if stopped {
+                cells.push(CellReport {
+                    cell_id,
+                    status: CellStatus::Skipped,
+                    execution_count: None,
+                    duration: Duration::ZERO,
+                });
+                continue;
+            }
+            let cell_started = Instant::now();
+            let run = while_alive(&kernel, async {
+                let run = session.run_cell(&cell_id);
+                tokio::pin!(run);
+                let timeout = match self.cell_timeout {
+                    Some(timeout) => timeout,
+                    None => return Some(run.await),
+                };
+                if let Ok(result) = tokio::time::timeout(timeout, &mut run).await {
+                    return Some(result);
+                }
+                // Let the interrupted cell finish, dropping it early could leave its handlers
+                // writing outputs after the notebook is copied
+                client.interrupt().await;
+                let _ = tokio::time::timeout(INTERRUPT_GRACE, run).await;
+                None
+            })
+            .await;
+            let duration = cell_started.elapsed();
+            let (status, execution_count) = match run {
+                Err(_) => (CellStatus::KernelDied, None),
+                Ok(None) => (CellStatus::TimedOut, None),
+                Ok(Some(result)) => {
+                    match result.expect("Only code cells are run") {
+                        ExecutionResult::Ok { execution_count } => {
+                            (CellStatus::Ok, execution_count)
+                        }
+                        ExecutionResult::Error {
+                            execution_count,
+                            ename,
+                            evalue,
+                            ..
+                        } if raises_exception => {
+                            (CellStatus::ExpectedError { ename, evalue }, execution_count)
+                        }
+                        ExecutionResult::Error {
+                            execution_count,
+                            ename,
+                            evalue,
+                            ..
+                        } => (CellStatus::Error { ename, evalue }, execution_count),
+                        ExecutionResult::HandlerAborted { errors } => {
+                            (CellStatus::HandlerAborted { errors }, None)
+                        }
+                        // Only one cell is sent at a time and nothing here cancels it, so these
+                        // don't come up
+                        ExecutionResult::Aborted | ExecutionResult::Cancelled => {
+                            (CellStatus::Skipped, None)
+                        }
+                        ExecutionResult::KernelDied => (CellStatus::KernelDied, None),
+                    }
+                }
+            };
+            stopped = match status {
+                CellStatus::Ok | CellStatus::ExpectedError { .. } => false,
+                CellStatus::Error { .. } => !self.allow_errors,
+                _ => true,
+            };
+            cells.push(CellReport {
+                cell_id,
+                status,
+                execution_count,
+                duration,
+            });
+        }
+
+        let executed = nb.lock().await.clone();
+        drop(session);
+        shutdown_gracefully(kernel, client).await;
+        let report = ExecutionReport {
+            kernel_name,
+            cells,
+            duration: started.elapsed(),
+            allow_errors: self.allow_errors,
+        };
+        Ok((executed, report))
+    }
+}
+
+// nbclient's tag for cells that are supposed to error
+fn raises_exception(cell: &Cell) -> bool {
+    cell.metadata()["tags"]
+        .as_array()
+        .is_some_and(|tags| tags.iter().any(|tag| tag == "raises-exception"))
+}
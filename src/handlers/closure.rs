@@ -0,0 +1,75 @@
+use std::fmt;
+use std::future::Future;
+
+use crate::handlers::{Handler, HandlerContext};
+use crate::jupyter::response::Response;
+use crate::notebook::Output;
+
+// Handler backed by a closure, for small reactions that don't deserve their own struct.
+// The closure gets its own copy of each message and the HandlerContext so the future it returns
+// can hold on to them.
+//
+// let handler = handler_fn(|msg: Response, _ctx: HandlerContext| async move {
+//     println!("{}", msg.msg_type());
+// });
+pub struct FnHandler<F> {
+    f: F,
+}
+
+pub fn handler_fn<F, Fut>(f: F) -> FnHandler<F>
+where
+    F: FnMut(Response, HandlerContext) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    FnHandler { f }
+}
+
+impl<F> fmt::Debug for FnHandler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnHandler").finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> Handler for FnHandler<F>
+where
+    F: FnMut(Response, HandlerContext) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext) {
+        (self.f)(msg.clone(), ctx.clone()).await;
+    }
+}
+
+// Like handler_fn, but only runs for messages that produce a cell Output (stream, display_data,
+// execute_result, error), see Output::from_response
+pub struct OutputFnHandler<F> {
+    f: F,
+}
+
+pub fn on_output<F, Fut>(f: F) -> OutputFnHandler<F>
+where
+    F: FnMut(Output, HandlerContext) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    OutputFnHandler { f }
+}
+
+impl<F> fmt::Debug for OutputFnHandler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputFnHandler").finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> Handler for OutputFnHandler<F>
+where
+    F: FnMut(Output, HandlerContext) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext) {
+        if let Some(output) = Output::from_response(msg) {
+            (self.f)(output, ctx.clone()).await;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::handlers::{Handler, HandlerContext};
+use crate::jupyter::response::Response;
+
+// Wrap any Handler to change which messages it sees
+//
+// let handler = SimpleOutputHandler::new()
+//     .filter_msg_type(&["stream"])
+//     .tee(MessageCountHandler::new());
+pub trait HandlerExt: Handler + Sized {
+    // Only pass along messages with one of these msg_types
+    fn filter_msg_type(self, msg_types: &[&str]) -> FilterMsgType<Self> {
+        FilterMsgType {
+            inner: self,
+            msg_types: msg_types.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    // Transform each message before it gets to this Handler
+    fn map<F>(self, f: F) -> Map<Self, F>
+    where
+        F: Fn(&Response) -> Response + Send + Sync,
+    {
+        Map { inner: self, f }
+    }
+
+    // Send every message to this Handler and then to another one
+    fn tee<H: Handler>(self, other: H) -> Tee<Self, H> {
+        Tee {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+impl<H: Handler> HandlerExt for H {}
+
+#[derive(Debug)]
+pub struct FilterMsgType<H> {
+    inner: H,
+    msg_types: HashSet<String>,
+}
+
+impl<H> FilterMsgType<H> {
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: Handler> Handler for FilterMsgType<H> {
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext) {
+        if self.msg_types.contains(&msg.msg_type()) {
+            self.inner.handle(msg, ctx).await;
+        }
+    }
+}
+
+pub struct Map<H, F> {
+    inner: H,
+    f: F,
+}
+
+impl<H, F> Map<H, F> {
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+}
+
+impl<H: fmt::Debug, F> fmt::Debug for Map<H, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Map")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl<H, F> Handler for Map<H, F>
+where
+    H: Handler,
+    F: Fn(&Response) -> Response + Send + Sync,
+{
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext) {
+        let mapped = (self.f)(msg);
+        self.inner.handle(&mapped, ctx).await;
+    }
+}
+
+#[derive(Debug)]
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Tee<A, B> {
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+#[async_trait::async_trait]
+impl<A: Handler, B: Handler> Handler for Tee<A, B> {
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext) {
+        self.first.handle(msg, ctx).await;
+        self.second.handle(msg, ctx).await;
+    }
+}
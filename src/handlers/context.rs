@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, WeakClient};
+use crate::jupyter::request::Request;
+
+// Passed to Handlers along with each message, for reacting to the Kernel by sending more requests
+// (answering a comm_msg, kicking off the next cell, ..)
+//
+// let handler = handler_fn(|msg: Response, ctx: HandlerContext| async move {
+//     if let (Response::Execute(_), Some(client)) = (msg, ctx.client()) {
+//         client.execute_request("print('next')".to_string(), vec![]).await;
+//     }
+// });
+//
+// Awaiting a follow-up Action inside handle blocks this Action's other messages until it's done,
+// spawn a task for that instead.
+#[derive(Debug, Clone)]
+pub struct HandlerContext {
+    request: Arc<Request>,
+    sent_at: Instant,
+    client: WeakClient,
+}
+
+impl HandlerContext {
+    pub fn new(request: Request, client: &Client) -> Self {
+        Self {
+            request: Arc::new(request),
+            sent_at: Instant::now(),
+            client: client.downgrade(),
+        }
+    }
+
+    // The request whose responses are being handled
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    pub fn msg_id(&self) -> String {
+        self.request.msg_id()
+    }
+
+    // When the request was sent to the Kernel
+    pub fn sent_at(&self) -> Instant {
+        self.sent_at
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.sent_at.elapsed()
+    }
+
+    // The Client the request went out on, None once every clone of it has been dropped. Pending
+    // Actions don't keep the Client alive, but holding on to the returned clone does.
+    pub fn client(&self) -> Option<Client> {
+        self.client.upgrade()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::handlers::Handler;
+use crate::handlers::{Handler, HandlerContext};
 use crate::jupyter::response::Response;
 
 // dbg!'s all messages handled by an Action
@@ -20,7 +20,7 @@ impl DebugHandler {
 
 #[async_trait::async_trait]
 impl Handler for DebugHandler {
-    async fn handle(&mut self, msg: &Response) {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
         dbg!(msg);
     }
 }
@@ -0,0 +1,181 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+use crate::handlers::set::HandlerEntry;
+use crate::handlers::{ErrorPolicy, HandlerContext, HandlerError, HandlerFailure, HandlerSet};
+use crate::jupyter::response::Response;
+
+// How an Action hands messages to the Handlers in its HandlerSet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchMode {
+    // Every Handler sees a message before the next message is handled, in HandlerSet order. A
+    // slow Handler holds up all the others.
+    #[default]
+    Sequential,
+    // Each Handler runs in its own task and gets messages (still in order) from a queue holding up
+    // to queue_size of them. Once a Handler's queue is full, the Action waits for it to catch up.
+    // The Action completes after every Handler has worked through its queue. With
+    // ErrorPolicy::Abort, other Handlers may already be past the message that failed.
+    Concurrent {
+        queue_size: usize,
+    },
+}
+
+#[derive(Debug)]
+struct HandlerQueue {
+    tx: mpsc::Sender<Response>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+enum Handlers {
+    Sequential(Vec<HandlerEntry>),
+    Concurrent(Vec<HandlerQueue>),
+}
+
+// Runs the Handlers for one Action according to its DispatchMode, collecting their errors
+#[derive(Debug)]
+pub(crate) struct Dispatcher {
+    handlers: Handlers,
+    ctx: HandlerContext,
+    errors: Arc<std::sync::Mutex<Vec<HandlerFailure>>>,
+    // Notified by a concurrent Handler failing with ErrorPolicy::Abort
+    abort: Arc<Notify>,
+    // Set along with that notify, so finish can tell even if the listener never saw it
+    abort_fired: Arc<AtomicBool>,
+}
+
+impl Dispatcher {
+    pub(crate) fn new(handlers: HandlerSet, ctx: HandlerContext) -> Self {
+        let errors = Arc::new(std::sync::Mutex::new(vec![]));
+        let abort = Arc::new(Notify::new());
+        let abort_fired = Arc::new(AtomicBool::new(false));
+        let (entries, mode) = handlers.into_parts();
+        let handlers = match mode {
+            DispatchMode::Sequential => Handlers::Sequential(entries),
+            DispatchMode::Concurrent { queue_size } => Handlers::Concurrent(
+                entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(handler_index, entry)| {
+                        let (tx, rx) = mpsc::channel(queue_size.max(1));
+                        let task = tokio::spawn(handler_worker(
+                            handler_index,
+                            entry,
+                            rx,
+                            ctx.clone(),
+                            errors.clone(),
+                            abort.clone(),
+                            abort_fired.clone(),
+                        ));
+                        HandlerQueue { tx, task }
+                    })
+                    .collect(),
+            ),
+        };
+        Self {
+            handlers,
+            ctx,
+            errors,
+            abort,
+            abort_fired,
+        }
+    }
+
+    // Hand a message to every Handler. Returns false if a Handler failed with ErrorPolicy::Abort,
+    // in concurrent mode that's reported through aborted instead.
+    pub(crate) async fn dispatch(&mut self, msg: &Response) -> bool {
+        match &self.handlers {
+            Handlers::Sequential(entries) => {
+                for (handler_index, entry) in entries.iter().enumerate() {
+                    if let Err((error, policy)) = entry.dispatch(msg, &self.ctx).await {
+                        record(&self.errors, handler_index, msg, error);
+                        if policy == ErrorPolicy::Abort {
+                            return false;
+                        }
+                    }
+                }
+            }
+            Handlers::Concurrent(queues) => {
+                for queue in queues {
+                    // The worker is only gone if it aborted, which aborted() picks up
+                    let _ = queue.tx.send(msg.clone()).await;
+                }
+            }
+        }
+        true
+    }
+
+    // Resolves once a concurrently running Handler fails with ErrorPolicy::Abort
+    pub(crate) async fn aborted(&self) {
+        self.abort.notified().await;
+    }
+
+    // Wait for every Handler to finish what's queued, then drop them and take the errors. The
+    // bool is true if a concurrent Handler failed with ErrorPolicy::Abort along the way.
+    pub(crate) async fn finish(&mut self) -> (bool, Vec<HandlerFailure>) {
+        if let Handlers::Concurrent(queues) = &mut self.handlers {
+            for queue in std::mem::take(queues) {
+                drop(queue.tx);
+                let _ = queue.task.await;
+            }
+        }
+        (self.abort_fired.load(Ordering::SeqCst), self.detach())
+    }
+
+    // Drop the Handlers right away, skipping anything still queued, and take the errors
+    pub(crate) fn detach(&mut self) -> Vec<HandlerFailure> {
+        match &mut self.handlers {
+            Handlers::Sequential(entries) => entries.clear(),
+            Handlers::Concurrent(queues) => {
+                for queue in queues.drain(..) {
+                    queue.task.abort();
+                }
+            }
+        }
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.detach();
+    }
+}
+
+fn record(
+    errors: &std::sync::Mutex<Vec<HandlerFailure>>,
+    handler_index: usize,
+    msg: &Response,
+    error: HandlerError,
+) {
+    errors.lock().unwrap().push(HandlerFailure {
+        handler_index,
+        msg_type: msg.msg_type(),
+        error: Arc::from(error),
+    });
+}
+
+async fn handler_worker(
+    handler_index: usize,
+    entry: HandlerEntry,
+    mut msg_rx: mpsc::Receiver<Response>,
+    ctx: HandlerContext,
+    errors: Arc<std::sync::Mutex<Vec<HandlerFailure>>>,
+    abort: Arc<Notify>,
+    abort_fired: Arc<AtomicBool>,
+) {
+    while let Some(msg) = msg_rx.recv().await {
+        if let Err((error, policy)) = entry.dispatch(&msg, &ctx).await {
+            record(&errors, handler_index, &msg, error);
+            if policy == ErrorPolicy::Abort {
+                abort_fired.store(true, Ordering::SeqCst);
+                abort.notify_one();
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::handlers::HandlerContext;
+use crate::jupyter::response::Response;
+
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+// Handler that can fail. Errors end up on the ActionOutcome, and the ErrorPolicy it was added to
+// the HandlerSet with decides whether the Action keeps going.
+#[async_trait::async_trait]
+pub trait FallibleHandler: Debug + Send + Sync {
+    async fn try_handle(
+        &mut self,
+        msg: &Response,
+        ctx: &HandlerContext,
+    ) -> Result<(), HandlerError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    // Record the error and keep handling messages
+    #[default]
+    Continue,
+    // Record the error and stop the Action, it resolves with ActionStatus::Aborted. The Kernel
+    // keeps running the request, use Client::interrupt to stop that too.
+    Abort,
+}
+
+// One error from a FallibleHandler, see ActionOutcome::errors
+#[derive(Debug, Clone)]
+pub struct HandlerFailure {
+    // Position of the handler in its HandlerSet
+    pub handler_index: usize,
+    // msg_type of the message being handled when it failed
+    pub msg_type: String,
+    pub error: Arc<dyn std::error::Error + Send + Sync>,
+}
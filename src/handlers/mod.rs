@@ -1,16 +1,28 @@
 use crate::jupyter::response::Response;
 use std::fmt::Debug;
 
+pub mod closure;
+pub mod combinators;
+pub mod context;
 pub mod debug;
+pub mod dispatch;
+pub mod fallible;
 pub mod msg_count;
 pub mod outputs;
+pub mod set;
 
 // export Handlers
+pub use closure::{handler_fn, on_output};
+pub use combinators::HandlerExt;
+pub use context::HandlerContext;
 pub use debug::DebugHandler;
+pub use dispatch::DispatchMode;
+pub use fallible::{ErrorPolicy, FallibleHandler, HandlerError, HandlerFailure};
 pub use msg_count::MessageCountHandler;
 pub use outputs::SimpleOutputHandler;
+pub use set::HandlerSet;
 
 #[async_trait::async_trait]
 pub trait Handler: Debug + Send + Sync {
-    async fn handle(&mut self, msg: &Response);
+    async fn handle(&mut self, msg: &Response, ctx: &HandlerContext);
 }
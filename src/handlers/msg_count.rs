@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::jupyter::response::Response;
 
-use crate::handlers::Handler;
+use crate::handlers::{Handler, HandlerContext};
 
 // Returns a hashmap of {msg_type: count} for all messages handled by an Action
 // Primarily used in tests and introspective click-testing
@@ -27,7 +27,7 @@ impl MessageCountHandler {
 
 #[async_trait::async_trait]
 impl Handler for MessageCountHandler {
-    async fn handle(&mut self, msg: &Response) {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
         let msg_type = msg.msg_type();
         let count = self.counts.entry(msg_type).or_insert(0);
         *count += 1;
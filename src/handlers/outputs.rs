@@ -1,18 +1,33 @@
+use chrono::DateTime;
 use tokio::sync::Mutex;
 
-use crate::handlers::Handler;
+use crate::handlers::{FallibleHandler, Handler, HandlerContext, HandlerError};
+use crate::jupyter::iopub_content::status::KernelStatus;
 use crate::jupyter::response::Response;
-use crate::notebook::{Notebook, Output};
+use crate::notebook::{append_output, Cell, Notebook, Output};
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::sync::Arc;
 
-// Update a document model with outputs while running a cell
+// The cell an OutputHandler was writing to isn't in the Notebook anymore
+#[derive(Debug)]
+pub struct CellNotFound(pub String);
+
+impl fmt::Display for CellNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cell {} not found in Notebook", self.0)
+    }
+}
+
+impl std::error::Error for CellNotFound {}
+
+// Update a document model with outputs and execution_count while running a cell
 #[derive(Debug)]
 pub struct OutputHandler {
     nb: Arc<Mutex<Notebook>>,
     cell_id: String,
     clear_on_next_output: bool,
+    record_timing: bool,
 }
 
 impl OutputHandler {
@@ -21,13 +36,35 @@ impl OutputHandler {
             nb,
             cell_id: cell_id.to_string(),
             clear_on_next_output: false,
+            record_timing: false,
+        }
+    }
+
+    // Also write the cell's metadata.execution timestamps like JupyterLab's "record timing"
+    // setting, taken from the dates on Kernel messages
+    pub fn with_timing(mut self) -> Self {
+        self.record_timing = true;
+        self
+    }
+
+    async fn update_cell(&self, f: impl FnOnce(&mut Cell)) {
+        let mut nb = self.nb.lock().await;
+        if let Some(cell) = nb.get_mut_cell(&self.cell_id) {
+            f(cell);
         }
     }
 
     pub async fn add_output(&mut self, content: Output) {
         let mut nb = self.nb.lock().await;
+        let display_id = content
+            .as_display_data()
+            .and_then(|d| d.transient.as_ref())
+            .map(|t| t.display_id.clone());
         if let Some(cell) = nb.get_mut_cell(&self.cell_id) {
             cell.add_output(content);
+            if let Some(display_id) = display_id {
+                nb.track_display(&display_id, &self.cell_id);
+            }
         }
     }
 
@@ -41,7 +78,7 @@ impl OutputHandler {
 
 #[async_trait::async_trait]
 impl Handler for OutputHandler {
-    async fn handle(&mut self, msg: &Response) {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
         match msg {
             Response::ExecuteResult(m) => {
                 let output = Output::ExecuteResult(m.content.clone());
@@ -52,7 +89,7 @@ impl Handler for OutputHandler {
                 self.add_output(output).await;
             }
             Response::Stream(m) => {
-                let output = Output::Stream(m.content.clone());
+                let output = Output::Stream(m.content.clone().into());
                 if self.clear_on_next_output {
                     self.clear_output().await;
                     self.clear_on_next_output = false;
@@ -82,11 +119,88 @@ impl Handler for OutputHandler {
                     self.clear_output().await;
                 }
             }
+            Response::Status(m) if self.record_timing => {
+                let date = m.header.date();
+                match m.content.execution_state {
+                    KernelStatus::Busy => {
+                        self.update_cell(|cell| {
+                            cell.clear_timing();
+                            cell.record_timing("iopub.status.busy", date);
+                        })
+                        .await
+                    }
+                    KernelStatus::Idle => {
+                        self.update_cell(|cell| cell.record_timing("iopub.status.idle", date))
+                            .await
+                    }
+                    _ => {}
+                }
+            }
+            Response::ExecuteInput(m) => {
+                let record_timing = self.record_timing;
+                self.update_cell(|cell| {
+                    cell.set_execution_count(Some(m.content.execution_count));
+                    if record_timing {
+                        cell.record_timing("iopub.execute_input", m.header.date());
+                    }
+                })
+                .await
+            }
+            Response::Execute(m) => {
+                let record_timing = self.record_timing;
+                // Kernels put when they started running the request in the reply metadata
+                let started = m
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.get("started"))
+                    .and_then(|started| started.as_str())
+                    .and_then(|started| DateTime::parse_from_rfc3339(started).ok());
+                self.update_cell(|cell| {
+                    if m.content.execution_count.is_some() {
+                        cell.set_execution_count(m.content.execution_count);
+                    }
+                    if record_timing {
+                        if let Some(started) = started {
+                            cell.record_timing("shell.execute_reply.started", started.into());
+                        }
+                        cell.record_timing("shell.execute_reply", m.header.date());
+                    }
+                })
+                .await
+            }
+            // Updates can be for displays in any cell, not just the one this handler writes to.
+            // Only updates sent while running a request get here, since they're routed by parent
+            // msg id like everything else.
+            Response::UpdateDisplayData(m) => {
+                self.nb.lock().await.update_display_data(&m.content);
+            }
             _ => {}
         }
     }
 }
 
+// As a FallibleHandler, outputs for a cell that was deleted mid-run are an error instead of being
+// silently dropped
+#[async_trait::async_trait]
+impl FallibleHandler for OutputHandler {
+    async fn try_handle(
+        &mut self,
+        msg: &Response,
+        ctx: &HandlerContext,
+    ) -> Result<(), HandlerError> {
+        let changes_cell = Output::from_response(msg).is_some()
+            || matches!(
+                msg,
+                Response::ClearOutput(_) | Response::ExecuteInput(_) | Response::Execute(_)
+            );
+        if changes_cell && self.nb.lock().await.get_cell(&self.cell_id).is_none() {
+            return Err(Box::new(CellNotFound(self.cell_id.clone())));
+        }
+        self.handle(msg, ctx).await;
+        Ok(())
+    }
+}
+
 // SimpleOutputHandler doesn't update a document model, just stores a list of outputs in memory.
 // Useful for tests and maybe debug / demos, probably not something you care about for app building
 #[derive(Debug, Clone)]
@@ -113,7 +227,7 @@ impl SimpleOutputHandler {
     }
 
     async fn add_output(&mut self, content: Output) {
-        self.output.push(content);
+        append_output(&mut self.output, content);
         println!("adding output");
     }
 
@@ -125,7 +239,7 @@ impl SimpleOutputHandler {
 
 #[async_trait::async_trait]
 impl Handler for SimpleOutputHandler {
-    async fn handle(&mut self, msg: &Response) {
+    async fn handle(&mut self, msg: &Response, _ctx: &HandlerContext) {
         match msg {
             Response::ExecuteResult(m) => {
                 let output = Output::ExecuteResult(m.content.clone());
@@ -136,7 +250,7 @@ impl Handler for SimpleOutputHandler {
                 self.add_output(output).await;
             }
             Response::Stream(m) => {
-                let output = Output::Stream(m.content.clone());
+                let output = Output::Stream(m.content.clone().into());
                 if self.clear_on_next_output {
                     self.clear_output().await;
                     self.clear_on_next_output = false;
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::handlers::{
+    DispatchMode, ErrorPolicy, FallibleHandler, Handler, HandlerContext, HandlerError,
+};
+use crate::jupyter::response::Response;
+
+#[derive(Debug, Clone)]
+pub(crate) enum HandlerEntry {
+    Infallible(Arc<Mutex<dyn Handler>>),
+    Fallible(Arc<Mutex<dyn FallibleHandler>>, ErrorPolicy),
+}
+
+impl HandlerEntry {
+    pub(crate) async fn dispatch(
+        &self,
+        msg: &Response,
+        ctx: &HandlerContext,
+    ) -> Result<(), (HandlerError, ErrorPolicy)> {
+        match self {
+            HandlerEntry::Infallible(handler) => {
+                handler.lock().await.handle(msg, ctx).await;
+                Ok(())
+            }
+            HandlerEntry::Fallible(handler, policy) => handler
+                .lock()
+                .await
+                .try_handle(msg, ctx)
+                .await
+                .map_err(|e| (e, *policy)),
+        }
+    }
+}
+
+// The Handlers for one request. Each one is shared behind Arc<Mutex<..>> so the caller can keep a
+// handle and look at its state after the Action completes.
+//
+// let counts = Arc::new(Mutex::new(MessageCountHandler::new()));
+// let handlers = HandlerSet::new().shared(counts.clone()).with(DebugHandler::new());
+// client.execute_request(code, handlers).await.await;
+//
+// Handlers run one after another unless the set is switched to DispatchMode::Concurrent.
+#[derive(Debug, Clone, Default)]
+pub struct HandlerSet {
+    entries: Vec<HandlerEntry>,
+    mode: DispatchMode,
+}
+
+impl HandlerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a Handler the caller doesn't need to get back
+    pub fn with<H: Handler + 'static>(self, handler: H) -> Self {
+        self.shared(Arc::new(Mutex::new(handler)))
+    }
+
+    // Add a Handler the caller keeps a clone of
+    pub fn shared(mut self, handler: Arc<Mutex<dyn Handler>>) -> Self {
+        self.push(handler);
+        self
+    }
+
+    pub fn push(&mut self, handler: Arc<Mutex<dyn Handler>>) {
+        self.entries.push(HandlerEntry::Infallible(handler));
+    }
+
+    pub fn with_fallible<H: FallibleHandler + 'static>(
+        self,
+        handler: H,
+        policy: ErrorPolicy,
+    ) -> Self {
+        self.shared_fallible(Arc::new(Mutex::new(handler)), policy)
+    }
+
+    pub fn shared_fallible(
+        mut self,
+        handler: Arc<Mutex<dyn FallibleHandler>>,
+        policy: ErrorPolicy,
+    ) -> Self {
+        self.entries.push(HandlerEntry::Fallible(handler, policy));
+        self
+    }
+
+    pub fn dispatch_mode(mut self, mode: DispatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<HandlerEntry>, DispatchMode) {
+        (self.entries, self.mode)
+    }
+}
+
+impl From<Vec<Arc<Mutex<dyn Handler>>>> for HandlerSet {
+    fn from(handlers: Vec<Arc<Mutex<dyn Handler>>>) -> Self {
+        Self {
+            entries: handlers.into_iter().map(HandlerEntry::Infallible).collect(),
+            mode: DispatchMode::default(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+/*
+Kernels answer on the heartbeat channel from a separate thread, so a missing pong means the Kernel
+process is gone or wedged badly, not that it's busy running code. Client::wait_for_ready pings until
+the first pong or a timeout, and Client::start_heartbeat_monitor keeps pinging in the background and
+publishes Liveness transitions.
+
+Ref: https://jupyter-client.readthedocs.io/en/latest/messaging.html#heartbeat-for-kernels
+*/
+use std::fmt;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use zeromq::{ReqSocket, Socket, SocketRecv, SocketSend, ZmqMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    // Answered the last ping. Assumed until a heartbeat monitor says otherwise.
+    Alive,
+    // Missed some pings, but fewer than HeartbeatConfig::max_missed
+    Unresponsive,
+    // Missed max_missed pings in a row. Pending Actions finish with ActionStatus::KernelDied.
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    // Time between pings
+    pub interval: Duration,
+    // How long to wait for each pong
+    pub timeout: Duration,
+    // Consecutive missed pings before the Kernel is considered Dead
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(1),
+            max_missed: 5,
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_missed(mut self, max_missed: u32) -> Self {
+        self.max_missed = max_missed;
+        self
+    }
+}
+
+// Returned from Client::wait_for_ready when the Kernel never answered a ping
+#[derive(Debug, Clone)]
+pub struct HeartbeatTimeout(pub Duration);
+
+impl fmt::Display for HeartbeatTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Kernel did not answer heartbeat within {:?}", self.0)
+    }
+}
+
+impl std::error::Error for HeartbeatTimeout {}
+
+// One ping / pong round trip. A REQ socket that missed its reply can't send again, so every ping
+// gets a fresh socket.
+pub(crate) async fn ping(address: &str, timeout: Duration) -> bool {
+    let attempt = async {
+        let mut socket = ReqSocket::new();
+        socket.connect(address).await.ok()?;
+        socket.send(ZmqMessage::from("ping")).await.ok()?;
+        socket.recv().await.ok()
+    };
+    matches!(tokio::time::timeout(timeout, attempt).await, Ok(Some(_)))
+}
+
+// Only wakes up subscribers when the state actually changes
+pub(crate) fn publish(liveness: &watch::Sender<Liveness>, state: Liveness) {
+    liveness.send_if_modified(|current| {
+        let changed = *current != state;
+        *current = state;
+        changed
+    });
+}
+
+pub(crate) async fn monitor_worker(
+    address: String,
+    config: HeartbeatConfig,
+    liveness: watch::Sender<Liveness>,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut missed = 0;
+    loop {
+        interval.tick().await;
+        if ping(&address, config.timeout).await {
+            missed = 0;
+            publish(&liveness, Liveness::Alive);
+        } else {
+            missed += 1;
+            let state = if missed >= config.max_missed {
+                Liveness::Dead
+            } else {
+                Liveness::Unresponsive
+            };
+            publish(&liveness, state);
+        }
+    }
+}
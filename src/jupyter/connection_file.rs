@@ -1,11 +1,12 @@
+use crate::jupyter::paths::jupyter_runtime_dir;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::fs::{self, File};
+use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
 use std::net::TcpListener;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConnectionInfo {
@@ -27,6 +28,21 @@ fn find_open_port() -> Result<u16, std::io::Error> {
         .map(|addr| addr.port())
 }
 
+// Create the Jupyter runtime dir if needed. Like jupyter_core, it's only readable by the owner since
+// connection files hold the HMAC key for talking to a Kernel.
+fn create_runtime_dir() -> Result<PathBuf, io::Error> {
+    let runtime_dir = jupyter_runtime_dir();
+    if !runtime_dir.exists() {
+        fs::create_dir_all(&runtime_dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&runtime_dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    Ok(runtime_dir)
+}
+
 fn generate_hmac_key() -> String {
     rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -58,42 +74,158 @@ impl ConnectionInfo {
         })
     }
 
+    // Use Unix domain sockets instead of TCP ports. Following jupyter_client, with the ipc transport
+    // the "ip" field is a path prefix and each channel binds to "<ip>-<port>", so the "ports" here
+    // are just small integers used to make those socket paths unique.
+    pub fn new_ipc(kernel_name: Option<String>) -> Result<Self, io::Error> {
+        let runtime_dir = create_runtime_dir()?;
+        let prefix = runtime_dir.join(format!(
+            "kernel-sidecar-ipc-{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        Ok(Self {
+            ip: prefix.to_string_lossy().to_string(),
+            transport: "ipc".to_string(),
+            shell_port: 1,
+            iopub_port: 2,
+            stdin_port: 3,
+            control_port: 4,
+            hb_port: 5,
+            signature_scheme: "hmac-sha256".to_string(),
+            key: generate_hmac_key(),
+            kernel_name,
+        })
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
         let file_contents = fs::read_to_string(path)?;
         serde_json::from_str(&file_contents).map_err(io::Error::from)
     }
 
+    // Connection files are written with 0600 permissions because anyone who can read the key can
+    // execute code in the Kernel
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
         let json = serde_json::to_string_pretty(self)?;
-        let mut file = File::create(path)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // mode only applies when the file is created, tighten up an existing file too
+            if path.as_ref().exists() {
+                fs::set_permissions(path.as_ref(), fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        let mut file = options.open(path)?;
         file.write_all(json.as_bytes())?;
         Ok(())
     }
 
-    pub fn to_temp_file(&self) -> Result<std::path::PathBuf, io::Error> {
-        let mut file_path = std::env::temp_dir();
-        if self.kernel_name.is_some() {
-            file_path.push(format!(
+    // Write a kernel-*.json connection file into the Jupyter runtime dir, which is where JupyterLab
+    // and jupyter_client look for running Kernels. This is the default for JupyterKernel.
+    pub fn to_runtime_file(&self) -> Result<PathBuf, io::Error> {
+        let mut file_path = create_runtime_dir()?;
+        file_path.push(self.connection_file_name());
+        self.to_file(&file_path)?;
+        Ok(file_path)
+    }
+
+    // List connection files for Kernels in the Jupyter runtime dir, e.g. ones started by JupyterLab,
+    // so the sidecar can attach to them. Files that can't be parsed are skipped. This doesn't check
+    // whether the Kernel is still alive, use Client heartbeats for that.
+    pub fn discover_running() -> Result<Vec<(PathBuf, Self)>, io::Error> {
+        let runtime_dir = jupyter_runtime_dir();
+        if !runtime_dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut running = vec![];
+        for entry in fs::read_dir(runtime_dir)? {
+            let path = entry?.path();
+            let is_connection_file = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with("kernel-") && name.ends_with(".json"))
+                .unwrap_or(false);
+            if !is_connection_file {
+                continue;
+            }
+            if let Ok(connection_info) = Self::from_file(&path) {
+                running.push((path, connection_info));
+            }
+        }
+        running.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(running)
+    }
+
+    fn connection_file_name(&self) -> String {
+        match &self.kernel_name {
+            Some(kernel_name) => format!(
                 "kernel-sidecar-{}-{}.json",
-                self.kernel_name.as_ref().unwrap(),
+                kernel_name,
                 uuid::Uuid::new_v4()
-            ));
-        } else {
-            file_path.push(format!("kernel-sidecar-{}.json", uuid::Uuid::new_v4()));
+            ),
+            None => format!("kernel-sidecar-{}.json", uuid::Uuid::new_v4()),
         }
+    }
+
+    pub fn to_temp_file(&self) -> Result<std::path::PathBuf, io::Error> {
+        let mut file_path = std::env::temp_dir();
+        file_path.push(self.connection_file_name());
         self.to_file(&file_path)?;
         Ok(file_path)
     }
 
+    pub fn kernel_name(&self) -> Option<&str> {
+        self.kernel_name.as_deref()
+    }
+
+    pub fn transport(&self) -> &str {
+        &self.transport
+    }
+
+    fn address(&self, port: u16) -> String {
+        match self.transport.as_str() {
+            "ipc" => format!("ipc://{}-{}", self.ip, port),
+            _ => format!("{}://{}:{}", self.transport, self.ip, port),
+        }
+    }
+
     pub fn iopub_address(&self) -> String {
-        format!("{}://{}:{}", self.transport, self.ip, self.iopub_port)
+        self.address(self.iopub_port)
     }
 
     pub fn shell_address(&self) -> String {
-        format!("{}://{}:{}", self.transport, self.ip, self.shell_port)
+        self.address(self.shell_port)
+    }
+
+    pub fn stdin_address(&self) -> String {
+        self.address(self.stdin_port)
+    }
+
+    pub fn control_address(&self) -> String {
+        self.address(self.control_port)
     }
 
     pub fn heartbeat_address(&self) -> String {
-        format!("{}://{}:{}", self.transport, self.ip, self.hb_port)
+        self.address(self.hb_port)
+    }
+
+    // Socket files the Kernel creates when using the ipc transport, empty for tcp. These aren't
+    // removed by every Kernel on shutdown, so whoever started the Kernel should clean them up.
+    pub fn ipc_paths(&self) -> Vec<PathBuf> {
+        if self.transport != "ipc" {
+            return vec![];
+        }
+        [
+            self.shell_port,
+            self.iopub_port,
+            self.stdin_port,
+            self.control_port,
+            self.hb_port,
+        ]
+        .iter()
+        .map(|port| PathBuf::from(format!("{}-{}", self.ip, port)))
+        .collect()
     }
 }
@@ -0,0 +1,37 @@
+/*
+Interrupting over the control channel instead of with SIGINT, works for Kernels whose interrupt_mode
+is "message" and for ipykernel regardless of interrupt_mode.
+
+Ref: https://jupyter-client.readthedocs.io/en/latest/messaging.html#kernel-interrupt
+*/
+use crate::jupyter::header::Header;
+use crate::jupyter::message::Message;
+use crate::jupyter::request::Request;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InterruptRequest {}
+
+impl Default for InterruptRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterruptRequest {
+    pub fn new() -> Self {
+        InterruptRequest {}
+    }
+}
+
+impl From<InterruptRequest> for Request {
+    fn from(req: InterruptRequest) -> Self {
+        let msg = Message {
+            header: Header::new("interrupt_request".to_owned()),
+            parent_header: None,
+            metadata: None,
+            content: req,
+        };
+        Request::Interrupt(msg)
+    }
+}
@@ -0,0 +1,34 @@
+/*
+Asking the Kernel to exit on its own, so it can clean up (flush history, stop subprocesses) before
+its process goes away.
+
+Ref: https://jupyter-client.readthedocs.io/en/latest/messaging.html#kernel-shutdown
+*/
+use crate::jupyter::header::Header;
+use crate::jupyter::message::Message;
+use crate::jupyter::request::Request;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShutdownRequest {
+    // Whether the Kernel is about to be restarted rather than shut down for good
+    pub restart: bool,
+}
+
+impl ShutdownRequest {
+    pub fn new(restart: bool) -> Self {
+        ShutdownRequest { restart }
+    }
+}
+
+impl From<ShutdownRequest> for Request {
+    fn from(req: ShutdownRequest) -> Self {
+        let msg = Message {
+            header: Header::new("shutdown_request".to_owned()),
+            parent_header: None,
+            metadata: None,
+            content: req,
+        };
+        Request::Shutdown(msg)
+    }
+}
@@ -28,6 +28,11 @@ impl Header {
             version: "5.3".to_string(),
         }
     }
+
+    // When the message was created, according to whoever sent it
+    pub fn date(&self) -> DateTime<Utc> {
+        self.date
+    }
 }
 
 impl From<Bytes> for Header {
@@ -6,7 +6,7 @@ use bytes::Bytes;
 
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ClearOutput {
     pub wait: bool,
 }
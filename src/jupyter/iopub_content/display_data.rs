@@ -44,7 +44,7 @@ impl From<Bytes> for DisplayData {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct UpdateDisplayData {
     pub data: HashMap<String, serde_json::Value>,
     pub metadata: serde_json::Value,
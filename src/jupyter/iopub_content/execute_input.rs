@@ -5,11 +5,10 @@ https://jupyter-client.readthedocs.io/en/latest/messaging.html#code-inputs
 use bytes::Bytes;
 use serde::Deserialize;
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ExecuteInput {
-    code: String,
-    execution_count: u32,
+    pub code: String,
+    pub execution_count: u32,
 }
 
 impl From<Bytes> for ExecuteInput {
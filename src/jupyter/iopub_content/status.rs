@@ -1,18 +1,56 @@
 /*
-Ref: https://jupyter-client.readthedocs.io/en/latest/messaging.html#kernel-info
+Ref: https://jupyter-client.readthedocs.io/en/latest/messaging.html#kernel-status
 */
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-#[serde(rename_all = "snake_case")]
+// Kernels only send busy / idle / starting, but other states show up from Jupyter server-side
+// tooling (restarting, dead, ...). Anything unrecognized ends up in Unknown instead of failing to
+// deserialize.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "String", into = "String")]
 pub enum KernelStatus {
     Busy,
     Idle,
     Starting,
+    Restarting,
+    Autorestarting,
+    Terminating,
+    Dead,
+    Unknown(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl From<String> for KernelStatus {
+    fn from(state: String) -> Self {
+        match state.as_str() {
+            "busy" => KernelStatus::Busy,
+            "idle" => KernelStatus::Idle,
+            "starting" => KernelStatus::Starting,
+            "restarting" => KernelStatus::Restarting,
+            "autorestarting" => KernelStatus::Autorestarting,
+            "terminating" => KernelStatus::Terminating,
+            "dead" => KernelStatus::Dead,
+            _ => KernelStatus::Unknown(state),
+        }
+    }
+}
+
+impl From<KernelStatus> for String {
+    fn from(status: KernelStatus) -> Self {
+        match status {
+            KernelStatus::Busy => "busy".to_string(),
+            KernelStatus::Idle => "idle".to_string(),
+            KernelStatus::Starting => "starting".to_string(),
+            KernelStatus::Restarting => "restarting".to_string(),
+            KernelStatus::Autorestarting => "autorestarting".to_string(),
+            KernelStatus::Terminating => "terminating".to_string(),
+            KernelStatus::Dead => "dead".to_string(),
+            KernelStatus::Unknown(state) => state,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Status {
     pub execution_state: KernelStatus,
 }
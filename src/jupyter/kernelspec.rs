@@ -0,0 +1,134 @@
+/*
+Kernelspecs are the kernel.json files Jupyter uses to describe how to start a Kernel. JupyterKernel
+can start a Kernel from one of these instead of the hardcoded constructors.
+
+Ref: https://jupyter-client.readthedocs.io/en/latest/kernels.html#kernel-specs
+*/
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::jupyter::paths::jupyter_path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KernelSpec {
+    // name is the directory the kernel.json lives in, not part of the file itself
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub resource_dir: PathBuf,
+    pub argv: Vec<String>,
+    pub display_name: String,
+    pub language: String,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub interrupt_mode: Option<String>,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+impl KernelSpec {
+    // Load <resource_dir>/kernel.json
+    pub fn from_dir<P: AsRef<Path>>(resource_dir: P) -> Result<Self, io::Error> {
+        let resource_dir = resource_dir.as_ref();
+        let contents = fs::read_to_string(resource_dir.join("kernel.json"))?;
+        let mut spec: KernelSpec = serde_json::from_str(&contents).map_err(io::Error::from)?;
+        spec.name = resource_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        spec.resource_dir = resource_dir.to_owned();
+        Ok(spec)
+    }
+
+    // All installed kernelspecs. When the same name is installed in several places, the one
+    // earliest in the Jupyter search path wins (same as `jupyter kernelspec list`)
+    pub fn list() -> Vec<Self> {
+        let mut specs: Vec<KernelSpec> = vec![];
+        for kernels_dir in jupyter_path("kernels") {
+            let entries = match fs::read_dir(&kernels_dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut found: Vec<KernelSpec> = entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| Self::from_dir(entry.path()).ok())
+                .collect();
+            found.sort_by(|a, b| a.name.cmp(&b.name));
+            for spec in found {
+                if !specs.iter().any(|s| s.name == spec.name) {
+                    specs.push(spec);
+                }
+            }
+        }
+        specs
+    }
+
+    // Look up an installed kernelspec by name, e.g. "python3" or "rust". Names are case-insensitive
+    pub fn find(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        for kernels_dir in jupyter_path("kernels") {
+            let entries = match fs::read_dir(&kernels_dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                if entry.file_name().to_string_lossy().to_lowercase() != name {
+                    continue;
+                }
+                if let Ok(spec) = Self::from_dir(entry.path()) {
+                    return Some(spec);
+                }
+            }
+        }
+        None
+    }
+
+    // argv with {resource_dir} filled in. {connection_file} is left for whoever writes the
+    // connection file to fill in
+    pub fn formatted_argv(&self) -> Vec<String> {
+        let resource_dir = self.resource_dir.to_string_lossy();
+        self.argv
+            .iter()
+            .map(|arg| arg.replace("{resource_dir}", &resource_dir))
+            .collect()
+    }
+
+    // env with ${VAR} references expanded from this process' environment. Unknown variables are
+    // left as-is, matching jupyter_client's use of Template.safe_substitute
+    pub fn formatted_env(&self) -> HashMap<String, String> {
+        self.env
+            .iter()
+            .map(|(key, value)| (key.clone(), expand_env_vars(value)))
+            .collect()
+    }
+}
+
+fn expand_env_vars(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) => {
+                let var = &after[..end];
+                match std::env::var(var) {
+                    Ok(val) => expanded.push_str(&val),
+                    Err(_) => expanded.push_str(&rest[start..start + 2 + end + 1]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
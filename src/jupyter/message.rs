@@ -13,7 +13,7 @@ use crate::jupyter::header::Header;
 use crate::jupyter::metadata::Metadata;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message<T> {
     pub header: Header,
     pub parent_header: Option<Header>,
@@ -14,3 +14,9 @@ impl From<Bytes> for Metadata {
         Metadata(serde_json::from_slice(&bytes).expect("Error deserializing metadata"))
     }
 }
+
+impl Metadata {
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.0.get(key)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod control_content;
 pub mod header;
 pub mod iopub_content;
 pub mod metadata;
@@ -6,6 +7,8 @@ pub mod wire_protocol;
 
 pub mod connection_file;
 pub mod constants;
+pub mod kernelspec;
 pub mod message;
+pub mod paths;
 pub mod request;
 pub mod response;
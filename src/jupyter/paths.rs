@@ -0,0 +1,68 @@
+/*
+Helpers for locating the standard Jupyter directories. These mirror jupyter_core.paths so that files
+the sidecar writes (connection files, ipc sockets) live in the same places JupyterLab expects.
+
+Ref: https://docs.jupyter.org/en/latest/use/jupyter-directories.html
+*/
+use std::path::PathBuf;
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+// JUPYTER_DATA_DIR, otherwise the platform default (~/.local/share/jupyter on Linux)
+pub fn jupyter_data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("JUPYTER_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    if cfg!(target_os = "macos") {
+        home_dir().join("Library").join("Jupyter")
+    } else if cfg!(windows) {
+        match std::env::var_os("APPDATA") {
+            Some(appdata) => PathBuf::from(appdata).join("jupyter"),
+            None => home_dir().join(".jupyter").join("data"),
+        }
+    } else {
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(xdg) if !xdg.is_empty() => PathBuf::from(xdg).join("jupyter"),
+            _ => home_dir().join(".local").join("share").join("jupyter"),
+        }
+    }
+}
+
+// JUPYTER_RUNTIME_DIR, otherwise <data dir>/runtime. Connection files and ipc sockets go here.
+pub fn jupyter_runtime_dir() -> PathBuf {
+    match std::env::var_os("JUPYTER_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => jupyter_data_dir().join("runtime"),
+    }
+}
+
+// Directories searched for Jupyter data files like kernelspecs, highest priority first:
+// JUPYTER_PATH, the user data dir, the active virtualenv / conda env, then system-wide dirs.
+// subdir is appended to each one, e.g. jupyter_path("kernels")
+pub fn jupyter_path(subdir: &str) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    if let Some(jupyter_path) = std::env::var_os("JUPYTER_PATH") {
+        paths.extend(std::env::split_paths(&jupyter_path).filter(|p| !p.as_os_str().is_empty()));
+    }
+    paths.push(jupyter_data_dir());
+    for prefix_var in ["VIRTUAL_ENV", "CONDA_PREFIX"] {
+        if let Some(prefix) = std::env::var_os(prefix_var) {
+            paths.push(PathBuf::from(prefix).join("share").join("jupyter"));
+        }
+    }
+    if cfg!(unix) {
+        paths.push(PathBuf::from("/usr/local/share/jupyter"));
+        paths.push(PathBuf::from("/usr/share/jupyter"));
+    }
+    let mut unique: Vec<PathBuf> = vec![];
+    for path in paths {
+        if !unique.contains(&path) {
+            unique.push(path);
+        }
+    }
+    unique.into_iter().map(|p| p.join(subdir)).collect()
+}
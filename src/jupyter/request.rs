@@ -6,15 +6,19 @@ message_content T -> Message<T> -> Request -> WireProtocol -> zeromq::ZmqMessage
 The impl's for message_content T -> Message<T> -> Request are in individual message_content files
 */
 
+use crate::jupyter::control_content::interrupt::InterruptRequest;
+use crate::jupyter::control_content::shutdown::ShutdownRequest;
 use crate::jupyter::message::Message;
 use crate::jupyter::shell_content::execute::ExecuteRequest;
 use crate::jupyter::shell_content::kernel_info::KernelInfoRequest;
 use crate::jupyter::wire_protocol::WireProtocol;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Request {
     KernelInfo(Message<KernelInfoRequest>),
     Execute(Message<ExecuteRequest>),
+    Interrupt(Message<InterruptRequest>),
+    Shutdown(Message<ShutdownRequest>),
 }
 
 impl Request {
@@ -23,6 +27,8 @@ impl Request {
         match self {
             Request::KernelInfo(msg) => msg.header.msg_id.to_owned(),
             Request::Execute(msg) => msg.header.msg_id.to_owned(),
+            Request::Interrupt(msg) => msg.header.msg_id.to_owned(),
+            Request::Shutdown(msg) => msg.header.msg_id.to_owned(),
         }
     }
 
@@ -38,6 +44,16 @@ impl Request {
                 Some(msg.content.clone()),
                 hmac_signing_key,
             ),
+            Request::Interrupt(msg) => WireProtocol::new(
+                msg.header.clone(),
+                Some(msg.content.clone()),
+                hmac_signing_key,
+            ),
+            Request::Shutdown(msg) => WireProtocol::new(
+                msg.header.clone(),
+                Some(msg.content.clone()),
+                hmac_signing_key,
+            ),
         }
     }
 }
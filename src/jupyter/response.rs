@@ -21,10 +21,14 @@ use serde::{Deserialize, Serialize};
 
 use zeromq::ZmqMessage;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UnmodeledContent(serde_json::Value);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmodeledContent(pub serde_json::Value);
 
-#[derive(Debug)]
+// KernelInfoReply (language_info, help_links, and now debugger and supported_features) is much
+// bigger than the other variants. Boxing it would make matching on Response clunkier everywhere for
+// a reply that's received about once per Client, and cached after that.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
 pub enum Response {
     // Request/reply from shell channel
     KernelInfo(Message<KernelInfoReply>),
@@ -32,6 +32,13 @@ impl ExecuteRequest {
             stop_on_error: true,
         }
     }
+
+    // When true (the default), an error in this cell makes the Kernel abort execute_requests
+    // already queued behind it, replying to them with status aborted
+    pub fn stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        self
+    }
 }
 
 impl From<ExecuteRequest> for Request {
@@ -46,11 +53,49 @@ impl From<ExecuteRequest> for Request {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+// Older Kernels reply "abort" instead of "aborted". Anything else unrecognized ends up in Unknown
+// instead of failing to deserialize, like KernelStatus.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "String", into = "String")]
+pub enum ExecuteStatus {
+    Ok,
+    Error,
+    Aborted,
+    Unknown(String),
+}
+
+impl From<String> for ExecuteStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "ok" => ExecuteStatus::Ok,
+            "error" => ExecuteStatus::Error,
+            "aborted" | "abort" => ExecuteStatus::Aborted,
+            _ => ExecuteStatus::Unknown(status),
+        }
+    }
+}
+
+impl From<ExecuteStatus> for String {
+    fn from(status: ExecuteStatus) -> Self {
+        match status {
+            ExecuteStatus::Ok => "ok".to_string(),
+            ExecuteStatus::Error => "error".to_string(),
+            ExecuteStatus::Aborted => "aborted".to_string(),
+            ExecuteStatus::Unknown(status) => status,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct ExecuteReply {
-    status: String,
-    execution_count: u32,
+    pub status: ExecuteStatus,
+    // Aborted replies don't always come with an execution_count
+    pub execution_count: Option<u32>,
+    // Set when status is error
+    pub ename: Option<String>,
+    pub evalue: Option<String>,
+    #[serde(default)]
+    pub traceback: Vec<String>,
 }
 
 impl From<Bytes> for ExecuteReply {
@@ -37,32 +37,48 @@ impl From<KernelInfoRequest> for Request {
 }
 
 // KernelInfoReply, related sub-structs, and impls
-#[derive(Serialize, Deserialize, Debug)]
-struct HelpLink {
-    text: String,
-    url: String,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HelpLink {
+    pub text: String,
+    pub url: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct LanguageInfo {
-    name: String,
-    version: String,
-    mimetype: String,
-    file_extension: String,
-    pygments_lexer: Option<String>,
-    codemirror_mode: Option<serde_json::Value>,
-    nbconvert_exporter: Option<String>,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LanguageInfo {
+    pub name: String,
+    pub version: String,
+    pub mimetype: String,
+    pub file_extension: String,
+    pub pygments_lexer: Option<String>,
+    pub codemirror_mode: Option<serde_json::Value>,
+    pub nbconvert_exporter: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct KernelInfoReply {
-    banner: String,
-    help_links: Option<Vec<HelpLink>>,
-    implementation: String,
-    implementation_version: String,
-    language_info: LanguageInfo,
-    protocol_version: String,
-    status: String,
+    pub banner: String,
+    pub help_links: Option<Vec<HelpLink>>,
+    pub implementation: String,
+    pub implementation_version: String,
+    pub language_info: LanguageInfo,
+    pub protocol_version: String,
+    pub status: String,
+    // Added in protocol 5.3, older Kernels don't send it and don't support debug requests
+    #[serde(default)]
+    pub debugger: bool,
+    // Added in protocol 5.5, optional protocol features the Kernel implements
+    #[serde(default)]
+    pub supported_features: Vec<String>,
+}
+
+impl KernelInfoReply {
+    // protocol_version as (major, minor), None if the Kernel sent something unparseable
+    pub fn protocol_version(&self) -> Option<(u32, u32)> {
+        let mut parts = self.protocol_version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().unwrap_or("0").parse().ok()?;
+        Some((major, minor))
+    }
 }
 
 impl From<Bytes> for KernelInfoReply {
@@ -0,0 +1,206 @@
+/*
+Resource limits and process isolation for Kernels running code we don't fully trust. Limits are
+applied with setrlimit in the forked child right before the Kernel command is exec'd, so they're
+inherited by anything the Kernel spawns.
+
+let limits = ResourceLimits::new()
+    .address_space(4 * 1024 * 1024 * 1024)
+    .cpu_seconds(600)
+    .open_files(256);
+let options = KernelLaunchOptions::new()
+    .limits(limits)
+    .process_group(ProcessGroup::NewSession);
+*/
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+use crate::kernels::logs::LogLine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimit {
+    AddressSpace,
+    CpuTime,
+    OpenFiles,
+    Processes,
+}
+
+// None means leave that limit as whatever this process has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub address_space: Option<u64>,
+    pub cpu_seconds: Option<u64>,
+    pub open_files: Option<u64>,
+    pub processes: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Max virtual memory in bytes (RLIMIT_AS)
+    pub fn address_space(mut self, bytes: u64) -> Self {
+        self.address_space = Some(bytes);
+        self
+    }
+
+    // Max CPU time in seconds (RLIMIT_CPU). The Kernel gets SIGXCPU when it's used up
+    pub fn cpu_seconds(mut self, seconds: u64) -> Self {
+        self.cpu_seconds = Some(seconds);
+        self
+    }
+
+    // Max open file descriptors (RLIMIT_NOFILE)
+    pub fn open_files(mut self, count: u64) -> Self {
+        self.open_files = Some(count);
+        self
+    }
+
+    // Max processes (RLIMIT_NPROC). Note this is counted per user, not per Kernel, so it needs to
+    // leave room for everything else running as the same user
+    pub fn processes(mut self, count: u64) -> Self {
+        self.processes = Some(count);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn is_set(&self, limit: ResourceLimit) -> bool {
+        match limit {
+            ResourceLimit::AddressSpace => self.address_space.is_some(),
+            ResourceLimit::CpuTime => self.cpu_seconds.is_some(),
+            ResourceLimit::OpenFiles => self.open_files.is_some(),
+            ResourceLimit::Processes => self.processes.is_some(),
+        }
+    }
+
+    // Whether the exit proves the Kernel was killed by one of these limits. Only running out of
+    // CPU time has a signal of its own: SIGXCPU at the soft limit, or SIGKILL from the hard limit
+    // if the Kernel caught SIGXCPU and kept going. Anyone can send a SIGKILL though, so that only
+    // counts if the CPU time the Kernel used (from its rusage) actually reached the limit. The
+    // other limits make syscalls fail instead, see likely_cause.
+    pub(crate) fn exceeded(
+        &self,
+        status: &ExitStatus,
+        cpu_time: Option<Duration>,
+    ) -> Option<ResourceLimit> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            let cpu_seconds = self.cpu_seconds?;
+            let used_up = cpu_time.is_some_and(|t| t >= Duration::from_secs(cpu_seconds));
+            match status.signal() {
+                Some(libc::SIGXCPU) => return Some(ResourceLimit::CpuTime),
+                Some(libc::SIGKILL) if used_up => return Some(ResourceLimit::CpuTime),
+                _ => {}
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (status, cpu_time);
+        None
+    }
+
+    // Guess at which limit a Kernel that died for no provable reason ran into, from the usual
+    // error messages in the last few log lines. Only a hint, user code can print these too.
+    pub(crate) fn likely_cause(
+        &self,
+        status: &ExitStatus,
+        logs: &[LogLine],
+    ) -> Option<ResourceLimit> {
+        if status.success() {
+            return None;
+        }
+        let patterns = [
+            (
+                ResourceLimit::AddressSpace,
+                &[
+                    "MemoryError",
+                    "Cannot allocate memory",
+                    "bad_alloc",
+                    "out of memory",
+                ][..],
+            ),
+            (ResourceLimit::OpenFiles, &["Too many open files"][..]),
+            (
+                ResourceLimit::Processes,
+                &["Resource temporarily unavailable", "fork: retry"][..],
+            ),
+        ];
+        let skip = logs.len().saturating_sub(50);
+        for line in logs.iter().skip(skip).rev() {
+            for (limit, messages) in patterns.iter() {
+                if self.is_set(*limit) && messages.iter().any(|m| line.line.contains(m)) {
+                    return Some(*limit);
+                }
+            }
+        }
+        None
+    }
+
+    #[cfg(unix)]
+    fn apply(&self) -> std::io::Result<()> {
+        // The CPU soft limit sends SIGXCPU, the hard limit one second later is a SIGKILL backstop
+        let limits = [
+            (libc::RLIMIT_AS, self.address_space, 0),
+            (libc::RLIMIT_CPU, self.cpu_seconds, 1),
+            (libc::RLIMIT_NOFILE, self.open_files, 0),
+            (libc::RLIMIT_NPROC, self.processes, 0),
+        ];
+        for (resource, value, hard_extra) in limits {
+            if let Some(value) = value {
+                let rlim = libc::rlimit {
+                    rlim_cur: value as libc::rlim_t,
+                    rlim_max: value.saturating_add(hard_extra) as libc::rlim_t,
+                };
+                if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Where the Kernel process sits relative to this process. With a new group or session the whole
+// tree of processes the Kernel started (multiprocessing workers, subprocesses) can be signalled at
+// once, and Ctrl-C in our terminal isn't delivered to the Kernel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessGroup {
+    #[default]
+    Inherit,
+    NewGroup,
+    NewSession,
+}
+
+// Set up the limits and process group in the child between fork and exec. Only async-signal-safe
+// calls are allowed in there, which setrlimit, setpgid and setsid all are.
+#[cfg(unix)]
+pub(crate) fn configure(command: &mut Command, limits: ResourceLimits, group: ProcessGroup) {
+    use std::os::unix::process::CommandExt;
+    if limits.is_empty() && group == ProcessGroup::Inherit {
+        return;
+    }
+    unsafe {
+        command.pre_exec(move || {
+            match group {
+                ProcessGroup::Inherit => {}
+                ProcessGroup::NewGroup => {
+                    if libc::setpgid(0, 0) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                ProcessGroup::NewSession => {
+                    if libc::setsid() < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+            }
+            limits.apply()
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn configure(_command: &mut Command, _limits: ResourceLimits, _group: ProcessGroup) {}
@@ -0,0 +1,137 @@
+/*
+Kernel stdout and stderr are piped into a bounded ring buffer so there is something to look at when a
+Kernel fails to start or dies mid-session. Lines can also be forwarded to callbacks as they arrive,
+and to tracing when the "tracing" feature is enabled.
+*/
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use chrono::{DateTime, Utc};
+
+// Default number of lines kept per Kernel, across both stdout and stderr
+pub const DEFAULT_LOG_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub source: LogSource,
+    pub line: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub type LogCallback = Arc<dyn Fn(&LogLine) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct KernelLogs {
+    capacity: usize,
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+    callbacks: Arc<Mutex<Vec<LogCallback>>>,
+}
+
+impl Debug for KernelLogs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KernelLogs")
+            .field("capacity", &self.capacity)
+            .field("len", &self.lines.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Default for KernelLogs {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+impl KernelLogs {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            callbacks: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    // Oldest lines are dropped once the buffer is full
+    pub fn push(&self, line: LogLine) {
+        // Call outside the lock so a callback can add more callbacks
+        let callbacks = self.callbacks.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(&line);
+        }
+        #[cfg(feature = "tracing")]
+        match line.source {
+            LogSource::Stdout => tracing::info!(target: "kernel_sidecar::kernel", "{}", line.line),
+            LogSource::Stderr => tracing::warn!(target: "kernel_sidecar::kernel", "{}", line.line),
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        if self.capacity > 0 {
+            lines.push_back(line);
+        }
+    }
+
+    pub fn add_callback(&self, callback: LogCallback) {
+        self.callbacks.lock().unwrap().push(callback);
+    }
+
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    // Text of the last n lines, handy for error messages
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        let skip = lines.len().saturating_sub(n);
+        lines.iter().skip(skip).map(|l| l.line.clone()).collect()
+    }
+
+    // Read lines from a Kernel stdout/stderr pipe on a background thread until the pipe closes.
+    // A plain thread instead of a tokio task since Kernels can be started outside of a runtime.
+    // When echo is set, lines are also printed to this process' stdout/stderr. The thread finishes
+    // once the pipe is closed and everything in it has been read.
+    pub(crate) fn capture<R: Read + Send + 'static>(
+        &self,
+        reader: R,
+        source: LogSource,
+        echo: bool,
+    ) -> JoinHandle<()> {
+        let logs = self.clone();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buf = vec![];
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                // Kernels don't always write valid utf-8, don't lose the rest of the log over it
+                let line = String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string();
+                if echo {
+                    match source {
+                        LogSource::Stdout => println!("{}", line),
+                        LogSource::Stderr => eprintln!("{}", line),
+                    }
+                }
+                logs.push(LogLine {
+                    source,
+                    line,
+                    timestamp: Utc::now(),
+                });
+            }
+        })
+    }
+}
@@ -0,0 +1,457 @@
+/*
+KernelManager starts Kernels from kernelspecs, pairs each one with a connected Client, and keeps
+track of them by id so an app can run many Kernels without juggling (JupyterKernel, Client) pairs.
+
+let manager = KernelManager::new();
+let id = manager.start("python3").await?;
+let client = manager.get(&id).unwrap();
+client.execute_request("2 + 2".to_string(), vec![]).await.await;
+manager.shutdown(&id).await?;
+
+Every Kernel is shut down when the KernelManager is dropped.
+
+Idle Kernels can be shut down automatically:
+
+manager.enable_culling(CullingConfig::new(Duration::from_secs(3600)).exempt_open_comms(true));
+*/
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::client::{Client, ReadyError};
+use crate::execution_queue::ExecutionResult;
+use crate::jupyter::kernelspec::KernelSpec;
+use crate::kernels::{JupyterKernel, KernelDied, KernelLaunchOptions};
+
+#[derive(Debug)]
+pub enum KernelManagerError {
+    SpecNotFound(String),
+    KernelNotFound(String),
+    KernelDied(KernelDied),
+    // The process is running, but the Kernel didn't answer in time, see Client::wait_until_ready
+    NotReady(ReadyError),
+    // A KernelPool's warm-up code didn't run cleanly, the Kernel was shut down
+    WarmupFailed(ExecutionResult),
+}
+
+impl fmt::Display for KernelManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KernelManagerError::SpecNotFound(name) => write!(f, "No kernelspec named {}", name),
+            KernelManagerError::KernelNotFound(id) => write!(f, "No Kernel with id {}", id),
+            KernelManagerError::KernelDied(died) => write!(f, "{}", died),
+            KernelManagerError::NotReady(error) => write!(f, "{}", error),
+            KernelManagerError::WarmupFailed(result) => {
+                write!(f, "Kernel warm-up failed: {:?}", result)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KernelManagerError {}
+
+impl From<KernelDied> for KernelManagerError {
+    fn from(died: KernelDied) -> Self {
+        KernelManagerError::KernelDied(died)
+    }
+}
+
+// Summary of a managed Kernel, returned from KernelManager::list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedKernelInfo {
+    pub id: String,
+    pub spec_name: String,
+    pub pid: u32,
+}
+
+#[derive(Debug)]
+pub(crate) struct ManagedKernel {
+    pub(crate) spec_name: String,
+    // From the kernelspec, "message" means interrupt over the control channel instead of SIGINT
+    interrupt_mode: Option<String>,
+    // Shared with restarts in progress, which run without holding the map lock
+    pub(crate) kernel: Arc<JupyterKernel>,
+    pub(crate) client: Client,
+    restarts: Arc<AtomicUsize>,
+}
+
+impl ManagedKernel {
+    async fn shutdown(self) {
+        shutdown_gracefully(self.kernel, self.client).await;
+    }
+
+    // For when there's no waiting around: gracefully in the background if there's a runtime,
+    // otherwise JupyterKernel kills its process on drop
+    fn shutdown_in_background(self) {
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(self.shutdown());
+            }
+            Err(_) => drop(self),
+        }
+    }
+}
+
+// Counts a restart as in progress until dropped, including when the restart future is dropped
+struct RestartGuard(Arc<AtomicUsize>);
+
+impl RestartGuard {
+    fn new(restarts: Arc<AtomicUsize>) -> Self {
+        restarts.fetch_add(1, Ordering::SeqCst);
+        Self(restarts)
+    }
+}
+
+impl Drop for RestartGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// JupyterKernel kills and waits on its process when dropped, do that off the async runtime if
+// there is one
+pub(crate) fn drop_blocking<T: Send + 'static>(value: T) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn_blocking(move || drop(value));
+        }
+        Err(_) => drop(value),
+    }
+}
+
+// How long a Kernel gets to exit after a shutdown_request before it's killed
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+// Give the Kernel a chance to exit on its own, then kill whatever is left off the runtime
+pub(crate) async fn shutdown_gracefully<K>(kernel: K, client: Client)
+where
+    K: Borrow<JupyterKernel> + Send + 'static,
+{
+    client.shutdown_kernel().await;
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, async {
+        while kernel.borrow().check_alive().is_ok() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    drop(client);
+    drop_blocking(kernel);
+}
+
+// Drive a future talking to the Kernel, bailing out if the process dies before it finishes
+pub(crate) async fn while_alive<F: Future>(
+    kernel: &JupyterKernel,
+    fut: F,
+) -> Result<F::Output, KernelDied> {
+    tokio::pin!(fut);
+    loop {
+        tokio::select! {
+            output = &mut fut => return Ok(output),
+            _ = tokio::time::sleep(Duration::from_millis(100)) => kernel.check_alive()?,
+        }
+    }
+}
+
+// How long a Kernel gets to come up after it's started or restarted
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Wait for the Kernel to come up with both shell and iopub connected, see Client::wait_until_ready
+pub(crate) async fn wait_until_ready(
+    kernel: &JupyterKernel,
+    client: &Client,
+) -> Result<(), KernelManagerError> {
+    while_alive(kernel, client.wait_until_ready(READY_TIMEOUT))
+        .await?
+        .map_err(KernelManagerError::NotReady)?;
+    Ok(())
+}
+
+// Start a Kernel from a kernelspec and connect a Client to it
+pub(crate) async fn start_connected(
+    spec: &KernelSpec,
+    options: KernelLaunchOptions,
+) -> Result<(JupyterKernel, Client), KernelManagerError> {
+    let kernel = JupyterKernel::from_spec(spec, options);
+    let client = Client::new(kernel.connection_info.clone()).await;
+    wait_until_ready(&kernel, &client).await?;
+    Ok((kernel, client))
+}
+
+// When to shut down idle Kernels, see KernelManager::enable_culling. Idle time is measured from
+// the later of the last idle status and the last request sent, see Client::idle_for
+#[derive(Debug, Clone)]
+pub struct CullingConfig {
+    pub idle_timeout: Duration,
+    // How often to check for idle Kernels
+    pub interval: Duration,
+    // Keep Kernels that have comms open, e.g. widgets someone may still be interacting with
+    pub exempt_open_comms: bool,
+    // Only cull Kernels when nobody else is holding a Client from KernelManager::get
+    pub only_when_disconnected: bool,
+}
+
+impl CullingConfig {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            interval: (idle_timeout / 10)
+                .clamp(Duration::from_millis(100), Duration::from_secs(60)),
+            exempt_open_comms: false,
+            only_when_disconnected: false,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn exempt_open_comms(mut self, exempt: bool) -> Self {
+        self.exempt_open_comms = exempt;
+        self
+    }
+
+    pub fn only_when_disconnected(mut self, only_when_disconnected: bool) -> Self {
+        self.only_when_disconnected = only_when_disconnected;
+        self
+    }
+
+    fn should_cull(&self, managed: &ManagedKernel) -> bool {
+        if managed.restarts.load(Ordering::SeqCst) > 0 {
+            return false;
+        }
+        if managed.client.idle_for() < self.idle_timeout {
+            return false;
+        }
+        if self.exempt_open_comms && !managed.client.activity().open_comms.is_empty() {
+            return false;
+        }
+        // The manager's own Client is always there, anything more is someone else's
+        if self.only_when_disconnected && managed.client.handle_count() > 1 {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KernelManager {
+    options: KernelLaunchOptions,
+    pub(crate) kernels: Arc<Mutex<HashMap<String, ManagedKernel>>>,
+    culler: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl KernelManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Launch options used for every Kernel started by this manager
+    pub fn with_options(options: KernelLaunchOptions) -> Self {
+        Self {
+            options,
+            kernels: Arc::new(Mutex::new(HashMap::new())),
+            culler: Mutex::new(None),
+        }
+    }
+
+    // Start a Kernel from an installed kernelspec and wait for it to be ready.
+    // Returns the id used to refer to it in the other KernelManager methods.
+    pub async fn start(&self, spec_name: &str) -> Result<String, KernelManagerError> {
+        self.start_with_options(spec_name, self.options.clone())
+            .await
+    }
+
+    pub async fn start_with_options(
+        &self,
+        spec_name: &str,
+        options: KernelLaunchOptions,
+    ) -> Result<String, KernelManagerError> {
+        let spec = KernelSpec::find(spec_name)
+            .ok_or_else(|| KernelManagerError::SpecNotFound(spec_name.to_string()))?;
+        let (kernel, client) = start_connected(&spec, options).await?;
+        Ok(self.adopt(&spec.name, kernel, client))
+    }
+
+    // Manage a Kernel started elsewhere, e.g. one handed out by a KernelPool
+    pub fn adopt(&self, spec_name: &str, kernel: JupyterKernel, client: Client) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let interrupt_mode = KernelSpec::find(spec_name).and_then(|spec| spec.interrupt_mode);
+        self.kernels.lock().unwrap().insert(
+            id.clone(),
+            ManagedKernel {
+                spec_name: spec_name.to_string(),
+                interrupt_mode,
+                kernel: Arc::new(kernel),
+                client,
+                restarts: Arc::new(AtomicUsize::new(0)),
+            },
+        );
+        id
+    }
+
+    pub fn list(&self) -> Vec<ManagedKernelInfo> {
+        let mut kernels: Vec<ManagedKernelInfo> = self
+            .kernels
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, managed)| ManagedKernelInfo {
+                id: id.clone(),
+                spec_name: managed.spec_name.clone(),
+                pid: managed.kernel.pid(),
+            })
+            .collect();
+        kernels.sort_by(|a, b| a.id.cmp(&b.id));
+        kernels
+    }
+
+    // The Client connected to a Kernel. Clients are cheap to clone and share the same ZMQ
+    // connections, but a restart replaces the Client so get it again afterwards.
+    pub fn get(&self, id: &str) -> Option<Client> {
+        self.kernels
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|managed| managed.client.clone())
+    }
+
+    // Interrupt whatever the Kernel is executing, the way its kernelspec's interrupt_mode says to
+    pub async fn interrupt(&self, id: &str) -> Result<(), KernelManagerError> {
+        let (kernel, client, by_message) = {
+            let kernels = self.kernels.lock().unwrap();
+            let managed = kernels
+                .get(id)
+                .ok_or_else(|| KernelManagerError::KernelNotFound(id.to_string()))?;
+            let by_message = managed.interrupt_mode.as_deref() == Some("message");
+            (managed.kernel.clone(), managed.client.clone(), by_message)
+        };
+        if by_message {
+            client.interrupt().await;
+        } else {
+            kernel.interrupt();
+        }
+        Ok(())
+    }
+
+    // Restart the Kernel process and connect a new Client to it. The Kernel stays managed while
+    // restarting, and dropping this future part way leaves it running rather than killing it.
+    pub async fn restart(&self, id: &str) -> Result<(), KernelManagerError> {
+        let (kernel, _restarting) = {
+            let kernels = self.kernels.lock().unwrap();
+            let managed = kernels
+                .get(id)
+                .ok_or_else(|| KernelManagerError::KernelNotFound(id.to_string()))?;
+            (
+                managed.kernel.clone(),
+                RestartGuard::new(managed.restarts.clone()),
+            )
+        };
+        let process = kernel.clone();
+        tokio::task::spawn_blocking(move || process.restart())
+            .await
+            .expect("Kernel restart panicked");
+        let client = Client::new(kernel.connection_info.clone()).await;
+        let replaced = match self.kernels.lock().unwrap().get_mut(id) {
+            Some(managed) => {
+                managed.client = client.clone();
+                true
+            }
+            None => false,
+        };
+        if !replaced {
+            // Shut down while restarting, this may be the last reference to the Kernel
+            drop_blocking(kernel);
+            return Err(KernelManagerError::KernelNotFound(id.to_string()));
+        }
+        wait_until_ready(&kernel, &client).await?;
+        Ok(())
+    }
+
+    // Ask the Kernel to exit with a shutdown_request, and kill it if it's still running after a
+    // grace period. It stops being managed right away.
+    pub async fn shutdown(&self, id: &str) -> Result<(), KernelManagerError> {
+        let managed = self
+            .kernels
+            .lock()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| KernelManagerError::KernelNotFound(id.to_string()))?;
+        managed.shutdown().await;
+        Ok(())
+    }
+
+    pub async fn shutdown_all(&self) {
+        let tasks: Vec<_> = self
+            .take_all()
+            .into_iter()
+            .map(|managed| tokio::spawn(managed.shutdown()))
+            .collect();
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+
+    // Empty the map, leaving shutting the Kernels down to the caller so it's not done under the lock
+    fn take_all(&self) -> Vec<ManagedKernel> {
+        let mut kernels = self.kernels.lock().unwrap();
+        kernels.drain().map(|(_, managed)| managed).collect()
+    }
+
+    // Start a background task that shuts down Kernels once they've been idle for too long.
+    // Calling this again replaces the previous config.
+    pub fn enable_culling(&self, config: CullingConfig) {
+        let task = tokio::spawn(culling_worker(Arc::downgrade(&self.kernels), config));
+        if let Some(previous) = self.culler.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+    }
+
+    pub fn disable_culling(&self) {
+        if let Some(task) = self.culler.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for KernelManager {
+    fn drop(&mut self) {
+        self.disable_culling();
+        for managed in self.take_all() {
+            managed.shutdown_in_background();
+        }
+    }
+}
+
+async fn culling_worker(
+    kernels: Weak<Mutex<HashMap<String, ManagedKernel>>>,
+    config: CullingConfig,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+        let kernels = match kernels.upgrade() {
+            Some(kernels) => kernels,
+            None => break,
+        };
+        // Removed under the lock, shut down outside it
+        let culled: Vec<ManagedKernel> = {
+            let mut kernels = kernels.lock().unwrap();
+            let idle: Vec<String> = kernels
+                .iter()
+                .filter(|(_, managed)| config.should_cull(managed))
+                .map(|(id, _)| id.clone())
+                .collect();
+            idle.iter().filter_map(|id| kernels.remove(id)).collect()
+        };
+        for managed in culled {
+            tokio::spawn(managed.shutdown());
+        }
+    }
+}
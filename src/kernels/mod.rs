@@ -0,0 +1,250 @@
+use crate::jupyter::connection_file::ConnectionInfo;
+use crate::jupyter::kernelspec::KernelSpec;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+pub mod limits;
+pub mod logs;
+pub mod manager;
+#[cfg(target_os = "linux")]
+pub mod monitor;
+pub mod options;
+pub mod pool;
+mod process;
+
+pub use limits::{ProcessGroup, ResourceLimit, ResourceLimits};
+pub use logs::{KernelLogs, LogLine, LogSource};
+pub use manager::{CullingConfig, KernelManager, KernelManagerError, ManagedKernelInfo};
+#[cfg(target_os = "linux")]
+pub use monitor::{KernelMonitor, MonitorConfig, ResourceSample, Threshold, ThresholdAction};
+pub use options::KernelLaunchOptions;
+pub use pool::{KernelPool, PoolSpec};
+use process::KernelProcess;
+
+// Lifecycle events for the Kernel process, see JupyterKernel::events
+#[derive(Debug, Clone)]
+pub enum KernelEvent {
+    // The Kernel process exited, either shut down from the Kernel side or crashed
+    Exited(ExitStatus),
+    // The Kernel process exited after running into one of its ResourceLimits
+    ResourceLimitExceeded {
+        limit: ResourceLimit,
+        status: ExitStatus,
+    },
+    // The Kernel process was killed and started again, see JupyterKernel::restart
+    Restarted,
+    // A KernelMonitor threshold was crossed, action is what the monitor did about it
+    #[cfg(target_os = "linux")]
+    ThresholdExceeded {
+        threshold: Threshold,
+        action: ThresholdAction,
+        sample: ResourceSample,
+    },
+}
+
+// Returned when a Kernel process has exited, includes the tail of its stdout/stderr for diagnostics
+#[derive(Debug, Clone)]
+pub struct KernelDied {
+    pub status: ExitStatus,
+    pub logs: Vec<LogLine>,
+    // Set when the exit status shows the Kernel was killed by one of its ResourceLimits
+    pub limit_exceeded: Option<ResourceLimit>,
+    // Otherwise, a limit the Kernel's last log lines suggest it ran into (e.g. a MemoryError with
+    // an address_space limit set). Only a hint, see ResourceLimits for what is detected.
+    pub likely_cause: Option<ResourceLimit>,
+}
+
+impl fmt::Display for KernelDied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit_exceeded {
+            Some(limit) => writeln!(
+                f,
+                "Kernel died ({}, {:?} limit exceeded)",
+                self.status, limit
+            )?,
+            None => match self.likely_cause {
+                Some(limit) => writeln!(
+                    f,
+                    "Kernel died ({}, possibly {:?} limit exceeded)",
+                    self.status, limit
+                )?,
+                None => writeln!(f, "Kernel died ({})", self.status)?,
+            },
+        }
+        // The full buffer is on .logs, just show the end of it here
+        let skip = self.logs.len().saturating_sub(20);
+        for line in self.logs.iter().skip(skip) {
+            writeln!(f, "  {}", line.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for KernelDied {}
+
+#[derive(Debug)]
+pub struct JupyterKernel {
+    process: Arc<KernelProcess>,
+    pub connection_info: ConnectionInfo,
+    pub connection_file: PathBuf,
+}
+
+impl JupyterKernel {
+    fn launch(
+        argv: Vec<String>,
+        spec_env: HashMap<String, String>,
+        connection_info: ConnectionInfo,
+        options: KernelLaunchOptions,
+    ) -> Self {
+        let file_path = connection_info.to_runtime_file().unwrap();
+        let file_path_str = file_path.to_str().unwrap();
+        let cmd = argv
+            .iter()
+            .map(|arg| arg.replace("{connection_file}", file_path_str))
+            .collect::<Vec<String>>();
+        let process = KernelProcess::spawn(cmd, spec_env, options);
+        Self {
+            process,
+            connection_info,
+            connection_file: file_path,
+        }
+    }
+
+    // Start any Kernel from an argv template. Like the argv in a kernelspec, "{connection_file}" is
+    // replaced with the path to the connection file written for this Kernel in the Jupyter runtime
+    // dir. Use this with ConnectionInfo::new_ipc to run a Kernel over Unix domain sockets instead
+    // of TCP ports.
+    pub fn start<O: Into<KernelLaunchOptions>>(
+        argv: Vec<&str>,
+        connection_info: ConnectionInfo,
+        options: O,
+    ) -> Self {
+        let argv = argv.iter().map(|arg| arg.to_string()).collect();
+        Self::launch(argv, HashMap::new(), connection_info, options.into())
+    }
+
+    // Start a Kernel from an installed kernelspec, see KernelSpec::find and KernelSpec::list
+    pub fn from_spec<O: Into<KernelLaunchOptions>>(spec: &KernelSpec, options: O) -> Self {
+        let options = options.into();
+        let connection_info = options.connection_info(&spec.name).unwrap();
+        let argv = spec.formatted_argv();
+        Self::launch(argv, spec.formatted_env(), connection_info, options)
+    }
+
+    fn builtin(kernel_name: &str, argv: Vec<&str>, options: KernelLaunchOptions) -> Self {
+        let connection_info = options.connection_info(kernel_name).unwrap();
+        let argv = argv.iter().map(|arg| arg.to_string()).collect();
+        Self::launch(argv, HashMap::new(), connection_info, options)
+    }
+
+    // pid changes when the Kernel is restarted
+    pub fn pid(&self) -> u32 {
+        self.process.pid()
+    }
+
+    // Subscribe to lifecycle events for the Kernel process. Events that happened before
+    // subscribing aren't replayed, use check_alive to see if the Kernel already exited.
+    pub fn events(&self) -> broadcast::Receiver<KernelEvent> {
+        self.process.events.subscribe()
+    }
+
+    // Snapshot of the most recent Kernel stdout/stderr lines
+    pub fn logs(&self) -> Vec<LogLine> {
+        self.process.logs.lines()
+    }
+
+    // Call f for every line the Kernel writes to stdout/stderr from now on. Lines written before
+    // this was called are still available from logs().
+    pub fn forward_logs<F>(&self, f: F)
+    where
+        F: Fn(&LogLine) + Send + Sync + 'static,
+    {
+        self.process.logs.add_callback(Arc::new(f));
+    }
+
+    // Err if the Kernel process has exited, e.g. crashed during startup
+    pub fn check_alive(&self) -> Result<(), KernelDied> {
+        match self.process.try_wait() {
+            Some(exit) => Err(self.process.died(exit)),
+            None => Ok(()),
+        }
+    }
+
+    // Send SIGINT to the Kernel (or its process group), interrupting whatever is executing.
+    // This is the "signal" interrupt_mode from kernelspecs, which every Kernel supports.
+    pub fn interrupt(&self) {
+        self.process.interrupt();
+    }
+
+    // Kill the Kernel process and start a fresh one with the same command and connection file.
+    // Existing Clients should be replaced since the old ZMQ sockets were talking to a dead process.
+    pub fn restart(&self) {
+        self.process.restart();
+    }
+
+    // Sample cpu / memory usage of the Kernel and everything it started, see KernelMonitor
+    #[cfg(target_os = "linux")]
+    pub fn monitor(&self, config: MonitorConfig) -> KernelMonitor {
+        KernelMonitor::start(Arc::downgrade(&self.process), config)
+    }
+
+    // start a Python (ipykernel) kernel
+    pub fn ipython<O: Into<KernelLaunchOptions>>(options: O) -> Self {
+        let argv = vec![
+            "python",
+            "-m",
+            "ipykernel_launcher",
+            "-f",
+            "{connection_file}",
+        ];
+        Self::builtin("ipykernel", argv, options.into())
+    }
+
+    // start a Rust (evcxr) kernel
+    pub fn evcxr<O: Into<KernelLaunchOptions>>(options: O) -> Self {
+        let argv = vec!["evcxr_jupyter", "--control_file", "{connection_file}"];
+        Self::builtin("evcxr", argv, options.into())
+    }
+
+    // Start an R (irkernel) kernel
+    pub fn irkernel<O: Into<KernelLaunchOptions>>(options: O) -> Self {
+        let argv = vec!["R", "-e", "IRkernel::main()", "--args", "{connection_file}"];
+        Self::builtin("ir", argv, options.into())
+    }
+
+    // Start a Typescript (deno) kernel
+    pub fn deno<O: Into<KernelLaunchOptions>>(options: O) -> Self {
+        let argv = vec![
+            "deno",
+            "jupyter",
+            "--unstable",
+            "--kernel",
+            "--conn",
+            "{connection_file}",
+        ];
+        Self::builtin("deno", argv, options.into())
+    }
+}
+
+impl Drop for JupyterKernel {
+    fn drop(&mut self) {
+        // In its own process group, this takes down anything the Kernel started along with it
+        self.process.kill();
+        self.connection_file
+            .as_path()
+            .to_owned()
+            .into_os_string()
+            .into_string()
+            .expect("Failed to convert connection_file to string");
+        std::fs::remove_file(&self.connection_file).expect("Failed to remove connection_file");
+        // Kernels using the ipc transport leave socket files behind, missing ones are fine since
+        // some Kernels clean up after themselves or never bound every channel
+        for path in self.connection_info.ipc_paths() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+/*
+Samples cpu and memory usage of a Kernel and every process it started from /proc. Samples are
+published on a watch channel so they can be read as a Stream or polled for the latest value, and
+thresholds can interrupt or restart the Kernel when it gets out of hand. Sampling runs on its own
+thread, so a monitor can be started with or without a tokio runtime.
+
+let config = MonitorConfig::new(Duration::from_secs(5))
+    .threshold(Threshold::RssBytes(8 * 1024 * 1024 * 1024), ThresholdAction::Restart)
+    .threshold(Threshold::CpuPercent(400.0), ThresholdAction::Interrupt);
+let monitor = kernel.monitor(config);
+let mut samples = monitor.samples();
+while let Some(sample) = samples.next().await {
+    println!("{} bytes rss", sample.rss_bytes);
+}
+*/
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::{mpsc, Weak};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::kernels::process::KernelProcess;
+use crate::kernels::KernelEvent;
+
+// Usage summed across the Kernel process and all of its descendants
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceSample {
+    pub timestamp: DateTime<Utc>,
+    pub pids: Vec<u32>,
+    pub rss_bytes: u64,
+    // total user + system cpu time consumed so far
+    pub cpu_seconds: f64,
+    // cpu usage since the previous sample, 100.0 is one full core
+    pub cpu_percent: f64,
+    pub threads: u64,
+    pub open_fds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    RssBytes(u64),
+    CpuPercent(f64),
+    CpuSeconds(f64),
+    Threads(u64),
+    OpenFds(u64),
+}
+
+impl Threshold {
+    fn exceeded_by(&self, sample: &ResourceSample) -> bool {
+        match *self {
+            Threshold::RssBytes(max) => sample.rss_bytes > max,
+            Threshold::CpuPercent(max) => sample.cpu_percent > max,
+            Threshold::CpuSeconds(max) => sample.cpu_seconds > max,
+            Threshold::Threads(max) => sample.threads > max,
+            Threshold::OpenFds(max) => sample.open_fds > max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdAction {
+    // Only publish a KernelEvent::ThresholdExceeded
+    Notify,
+    Interrupt,
+    Restart,
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    pub interval: Duration,
+    pub thresholds: Vec<(Threshold, ThresholdAction)>,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+impl MonitorConfig {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            thresholds: vec![],
+        }
+    }
+
+    pub fn threshold(mut self, threshold: Threshold, action: ThresholdAction) -> Self {
+        self.thresholds.push((threshold, action));
+        self
+    }
+}
+
+// Background sampling stops when the KernelMonitor or the JupyterKernel is dropped
+#[derive(Debug)]
+pub struct KernelMonitor {
+    samples: watch::Receiver<Option<ResourceSample>>,
+    // Dropping this wakes the sampling thread up and tells it to stop
+    _stop: mpsc::Sender<()>,
+}
+
+impl KernelMonitor {
+    pub(crate) fn start(process: Weak<KernelProcess>, config: MonitorConfig) -> Self {
+        let (tx, rx) = watch::channel(None);
+        let (stop_tx, stop_rx) = mpsc::channel();
+        std::thread::spawn(move || monitor_worker(process, config, tx, stop_rx));
+        Self {
+            samples: rx,
+            _stop: stop_tx,
+        }
+    }
+
+    pub fn latest(&self) -> Option<ResourceSample> {
+        self.samples.borrow().clone()
+    }
+
+    // Stream of samples, starting with the latest one if there is one. Ends when monitoring stops
+    pub fn samples(&self) -> impl Stream<Item = ResourceSample> {
+        WatchStream::new(self.samples.clone()).filter_map(|sample| sample)
+    }
+}
+
+fn monitor_worker(
+    process: Weak<KernelProcess>,
+    config: MonitorConfig,
+    tx: watch::Sender<Option<ResourceSample>>,
+    stop: mpsc::Receiver<()>,
+) {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+    let mut previous: Option<(Instant, u32, f64)> = None;
+    // Thresholds only fire when they're first crossed, not on every sample while above them
+    let mut tripped = vec![false; config.thresholds.len()];
+    let mut first = true;
+    loop {
+        // Sample right away, then once per interval until the KernelMonitor is dropped
+        if !first && stop.recv_timeout(config.interval) != Err(mpsc::RecvTimeoutError::Timeout) {
+            break;
+        }
+        first = false;
+        let process = match process.upgrade() {
+            Some(process) => process,
+            None => break,
+        };
+        let pid = process.pid();
+        let mut sample = match sample_tree(pid, ticks_per_second, page_size) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let now = Instant::now();
+        if let Some((then, previous_pid, previous_cpu)) = previous {
+            let elapsed = now.duration_since(then).as_secs_f64();
+            // after a restart the cpu counter starts over, skip that one reading
+            if previous_pid == pid && elapsed > 0.0 {
+                sample.cpu_percent =
+                    ((sample.cpu_seconds - previous_cpu) / elapsed * 100.0).max(0.0);
+            }
+        }
+        previous = Some((now, pid, sample.cpu_seconds));
+
+        for (i, (threshold, action)) in config.thresholds.iter().enumerate() {
+            let exceeded = threshold.exceeded_by(&sample);
+            if exceeded && !tripped[i] {
+                let _ = process.events.send(KernelEvent::ThresholdExceeded {
+                    threshold: *threshold,
+                    action: *action,
+                    sample: sample.clone(),
+                });
+                match action {
+                    ThresholdAction::Notify => {}
+                    ThresholdAction::Interrupt => process.interrupt(),
+                    ThresholdAction::Restart => process.restart(),
+                }
+            }
+            tripped[i] = exceeded;
+        }
+        if tx.send(Some(sample)).is_err() {
+            break;
+        }
+    }
+}
+
+// One process worth of numbers from /proc/<pid>/stat
+struct ProcStat {
+    ppid: u32,
+    cpu_ticks: u64,
+    threads: u64,
+    rss_pages: u64,
+}
+
+fn read_stat(pid: u32) -> Option<ProcStat> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // comm (field 2) is in parens and can contain spaces, so split after the last ')'
+    let rest = stat.get(stat.rfind(')')? + 2..)?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    // fields here start at field 3 (state) of proc(5)
+    Some(ProcStat {
+        ppid: fields.get(1)?.parse().ok()?,
+        cpu_ticks: fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?,
+        threads: fields.get(17)?.parse().ok()?,
+        rss_pages: fields.get(21)?.parse().ok()?,
+    })
+}
+
+// pid plus all of its descendants, found by walking the ppid of every process in /proc
+fn process_tree(pid: u32) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    if let Ok(entries) = fs::read_dir("/proc") {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let child: u32 = match entry.file_name().to_string_lossy().parse() {
+                Ok(child) => child,
+                Err(_) => continue,
+            };
+            if let Some(stat) = read_stat(child) {
+                children.entry(stat.ppid).or_default().push(child);
+            }
+        }
+    }
+    let mut tree = vec![];
+    let mut queue = VecDeque::from([pid]);
+    while let Some(next) = queue.pop_front() {
+        tree.push(next);
+        if let Some(kids) = children.get(&next) {
+            queue.extend(kids);
+        }
+    }
+    tree
+}
+
+fn sample_tree(pid: u32, ticks_per_second: f64, page_size: u64) -> Option<ResourceSample> {
+    // the Kernel itself has to be there, descendants can come and go between reads
+    read_stat(pid)?;
+    let mut sample = ResourceSample {
+        timestamp: Utc::now(),
+        pids: vec![],
+        rss_bytes: 0,
+        cpu_seconds: 0.0,
+        cpu_percent: 0.0,
+        threads: 0,
+        open_fds: 0,
+    };
+    for pid in process_tree(pid) {
+        let stat = match read_stat(pid) {
+            Some(stat) => stat,
+            None => continue,
+        };
+        sample.pids.push(pid);
+        sample.rss_bytes += stat.rss_pages * page_size;
+        sample.cpu_seconds += stat.cpu_ticks as f64 / ticks_per_second;
+        sample.threads += stat.threads;
+        sample.open_fds += fs::read_dir(format!("/proc/{}/fd", pid))
+            .map(|fds| fds.count() as u64)
+            .unwrap_or(0);
+    }
+    Some(sample)
+}
@@ -0,0 +1,147 @@
+/*
+Options for starting a Kernel process, shared by the built-in Kernel constructors and kernelspecs.
+
+let options = KernelLaunchOptions::new()
+    .cwd("/data/project")
+    .env("PYTHONPATH", "/data/project/src")
+    .interpreter("/data/project/.venv/bin/python")
+    .silent(true);
+let kernel = JupyterKernel::ipython(options);
+*/
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::jupyter::connection_file::ConnectionInfo;
+use crate::kernels::limits::{self, ProcessGroup, ResourceLimits};
+
+#[derive(Debug, Clone, Default)]
+pub struct KernelLaunchOptions {
+    pub(crate) silent: bool,
+    pub(crate) ipc: bool,
+    pub(crate) cwd: Option<PathBuf>,
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) clear_env: bool,
+    pub(crate) extra_args: Vec<String>,
+    pub(crate) interpreter: Option<PathBuf>,
+    pub(crate) limits: ResourceLimits,
+    pub(crate) process_group: ProcessGroup,
+}
+
+// The built-in Kernel constructors used to take a single silent flag, keep that working
+impl From<bool> for KernelLaunchOptions {
+    fn from(silent: bool) -> Self {
+        Self::new().silent(silent)
+    }
+}
+
+impl KernelLaunchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Don't echo Kernel stdout to this process' stdout. It's still captured in JupyterKernel::logs
+    pub fn silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+
+    // Use Unix domain sockets instead of TCP ports, see ConnectionInfo::new_ipc
+    pub fn ipc(mut self, ipc: bool) -> Self {
+        self.ipc = ipc;
+        self
+    }
+
+    pub fn cwd<P: Into<PathBuf>>(mut self, cwd: P) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    // Environment variables are merged on top of this process' environment and any env from the
+    // kernelspec, so these win when the same key is set in several places
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        for (key, value) in vars {
+            self.env.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    // Start the Kernel with only the env from the kernelspec and these options
+    pub fn clear_env(mut self, clear_env: bool) -> Self {
+        self.clear_env = clear_env;
+        self
+    }
+
+    // Extra arguments appended to the end of the Kernel command
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.extra_args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    // Replace the program in argv[0], e.g. point "python" at a virtualenv's python
+    pub fn interpreter<P: Into<PathBuf>>(mut self, interpreter: P) -> Self {
+        self.interpreter = Some(interpreter.into());
+        self
+    }
+
+    // rlimits applied to the Kernel process, see ResourceLimits
+    pub fn limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // Start the Kernel in its own process group or session so the whole process tree can be
+    // killed or interrupted together
+    pub fn process_group(mut self, process_group: ProcessGroup) -> Self {
+        self.process_group = process_group;
+        self
+    }
+
+    pub(crate) fn connection_info(&self, kernel_name: &str) -> Result<ConnectionInfo, io::Error> {
+        if self.ipc {
+            ConnectionInfo::new_ipc(Some(kernel_name.to_string()))
+        } else {
+            ConnectionInfo::new(Some(kernel_name.to_string()))
+        }
+    }
+
+    // Build the Command for a fully formatted argv (connection file already substituted) plus the
+    // env from a kernelspec, if there is one
+    pub(crate) fn command(&self, argv: &[String], spec_env: &HashMap<String, String>) -> Command {
+        let program = match &self.interpreter {
+            Some(interpreter) => interpreter.as_os_str().to_owned(),
+            None => argv[0].clone().into(),
+        };
+        let mut command = Command::new(program);
+        command.args(&argv[1..]).args(&self.extra_args);
+        if self.clear_env {
+            command.env_clear();
+        }
+        command.envs(spec_env).envs(&self.env);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        limits::configure(&mut command, self.limits, self.process_group);
+        command
+    }
+}
@@ -0,0 +1,235 @@
+/*
+KernelPool keeps Kernels started ahead of time so handing one out doesn't wait on the Kernel
+process to boot, which takes seconds for ipykernel or evcxr.
+
+let pool = KernelPool::new();
+pool.add(PoolSpec::new("python3", 2).warmup("import pandas"))?;
+let (kernel, client) = pool.acquire("python3").await?;
+
+Acquired Kernels belong to the caller, the pool starts a replacement in the background. When the
+pool is empty, acquire starts a Kernel on demand instead. A Kernel only counts as ready once its
+warm-up code ran without errors. Pooled Kernels are shut down when the KernelPool is dropped.
+*/
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::client::Client;
+use crate::execution_queue::{ExecutionResult, QueueMode};
+use crate::jupyter::kernelspec::KernelSpec;
+use crate::kernels::manager::{drop_blocking, start_connected, while_alive};
+use crate::kernels::{JupyterKernel, KernelLaunchOptions, KernelManagerError};
+
+// How many Kernels to keep ready for a kernelspec
+#[derive(Debug, Clone)]
+pub struct PoolSpec {
+    pub spec_name: String,
+    pub size: usize,
+    // Code run on each Kernel before it counts as ready, e.g. slow imports
+    pub warmup: Option<String>,
+    // Defaults to the KernelPool options
+    pub options: Option<KernelLaunchOptions>,
+}
+
+impl PoolSpec {
+    pub fn new(spec_name: &str, size: usize) -> Self {
+        Self {
+            spec_name: spec_name.to_string(),
+            size,
+            warmup: None,
+            options: None,
+        }
+    }
+
+    pub fn warmup(mut self, code: &str) -> Self {
+        self.warmup = Some(code.to_string());
+        self
+    }
+
+    pub fn options(mut self, options: KernelLaunchOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct SpecPool {
+    spec: KernelSpec,
+    size: usize,
+    warmup: Option<String>,
+    options: KernelLaunchOptions,
+    ready: Mutex<VecDeque<(JupyterKernel, Client)>>,
+    // Wakes up the refill task after a Kernel is handed out or the pool is closed
+    refill: Notify,
+    // Set when the pool is removed or replaced
+    closed: AtomicBool,
+}
+
+impl SpecPool {
+    async fn start(&self) -> Result<(JupyterKernel, Client), KernelManagerError> {
+        let (kernel, client) = start_connected(&self.spec, self.options.clone()).await?;
+        if let Some(code) = &self.warmup {
+            let queue = client
+                .execution_queue(QueueMode::Continue)
+                .push(code, vec![]);
+            let result = while_alive(&kernel, queue.run()).await?.remove(0);
+            if !matches!(result, ExecutionResult::Ok { .. }) {
+                drop_blocking((kernel, client));
+                return Err(KernelManagerError::WarmupFailed(result));
+            }
+        }
+        Ok((kernel, client))
+    }
+}
+
+// Closes the pool for a kernelspec when it's removed or replaced. The refill task is left to
+// finish any Kernel it's in the middle of starting and shut it down, rather than aborting it and
+// killing the Kernel from inside the async runtime.
+#[derive(Debug)]
+struct PoolEntry {
+    pool: Arc<SpecPool>,
+    _refill_task: JoinHandle<()>,
+}
+
+impl Drop for PoolEntry {
+    fn drop(&mut self) {
+        self.pool.closed.store(true, Ordering::SeqCst);
+        self.pool.refill.notify_one();
+        let ready = std::mem::take(&mut *self.pool.ready.lock().unwrap());
+        drop_blocking(ready);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KernelPool {
+    options: KernelLaunchOptions,
+    pools: Mutex<HashMap<String, PoolEntry>>,
+}
+
+impl KernelPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Launch options used for every pooled Kernel unless the PoolSpec sets its own
+    pub fn with_options(options: KernelLaunchOptions) -> Self {
+        Self {
+            options,
+            pools: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Start filling the pool for a kernelspec in the background. Adding a spec that's already
+    // pooled replaces it, shutting down its ready Kernels.
+    pub fn add(&self, pool_spec: PoolSpec) -> Result<(), KernelManagerError> {
+        let spec = KernelSpec::find(&pool_spec.spec_name)
+            .ok_or_else(|| KernelManagerError::SpecNotFound(pool_spec.spec_name.clone()))?;
+        let name = spec.name.clone();
+        let pool = Arc::new(SpecPool {
+            spec,
+            size: pool_spec.size,
+            warmup: pool_spec.warmup,
+            options: pool_spec.options.unwrap_or_else(|| self.options.clone()),
+            ready: Mutex::new(VecDeque::new()),
+            refill: Notify::new(),
+            closed: AtomicBool::new(false),
+        });
+        let refill_task = tokio::spawn(refill_worker(Arc::downgrade(&pool)));
+        let entry = PoolEntry {
+            pool,
+            _refill_task: refill_task,
+        };
+        let previous = self.pools.lock().unwrap().insert(name, entry);
+        drop(previous);
+        Ok(())
+    }
+
+    // Stop pooling a kernelspec and shut down its ready Kernels
+    pub fn remove(&self, spec_name: &str) {
+        let removed = self.pools.lock().unwrap().remove(&spec_name.to_lowercase());
+        drop(removed);
+    }
+
+    // Number of Kernels ready to be handed out for a kernelspec
+    pub fn available(&self, spec_name: &str) -> usize {
+        self.pool(spec_name)
+            .map(|pool| pool.ready.lock().unwrap().len())
+            .unwrap_or(0)
+    }
+
+    // Take a ready Kernel out of the pool, or start one if none are ready. Kernels for specs that
+    // aren't pooled are always started on demand.
+    pub async fn acquire(
+        &self,
+        spec_name: &str,
+    ) -> Result<(JupyterKernel, Client), KernelManagerError> {
+        let pool = match self.pool(spec_name) {
+            Some(pool) => pool,
+            None => {
+                let spec = KernelSpec::find(spec_name)
+                    .ok_or_else(|| KernelManagerError::SpecNotFound(spec_name.to_string()))?;
+                return start_connected(&spec, self.options.clone()).await;
+            }
+        };
+        loop {
+            let ready = pool.ready.lock().unwrap().pop_front();
+            // Only once one is taken out, otherwise the refill task could wake up to a full pool,
+            // use up the notify, and leave the pool one short
+            pool.refill.notify_one();
+            match ready {
+                // A pooled Kernel may have died while it was waiting around
+                Some((kernel, client)) => {
+                    if kernel.check_alive().is_ok() {
+                        return Ok((kernel, client));
+                    }
+                    drop_blocking((kernel, client));
+                }
+                None => return pool.start().await,
+            }
+        }
+    }
+
+    fn pool(&self, spec_name: &str) -> Option<Arc<SpecPool>> {
+        self.pools
+            .lock()
+            .unwrap()
+            .get(&spec_name.to_lowercase())
+            .map(|entry| entry.pool.clone())
+    }
+}
+
+async fn refill_worker(pool: Weak<SpecPool>) {
+    loop {
+        let pool = match pool.upgrade() {
+            Some(pool) => pool,
+            None => break,
+        };
+        if pool.closed.load(Ordering::SeqCst) {
+            break;
+        }
+        let missing = pool.size.saturating_sub(pool.ready.lock().unwrap().len());
+        if missing > 0 {
+            match pool.start().await {
+                Ok(started) => {
+                    // Checked under the ready lock so a Kernel can't land in a pool that was
+                    // just closed and emptied
+                    let mut ready = pool.ready.lock().unwrap();
+                    if pool.closed.load(Ordering::SeqCst) {
+                        drop(ready);
+                        drop_blocking(started);
+                        break;
+                    }
+                    ready.push_back(started);
+                }
+                // Don't spin on a kernelspec that keeps crashing, wait for the next acquire to
+                // try again. acquire surfaces the error when it has to start a Kernel itself.
+                Err(_) => pool.refill.notified().await,
+            }
+            continue;
+        }
+        pool.refill.notified().await;
+    }
+}
@@ -0,0 +1,338 @@
+/*
+Shared state for a running Kernel process. JupyterKernel owns it, and things that need to act on the
+Kernel from the background (exit watcher, KernelMonitor) hold a Weak reference so they never keep
+a Kernel alive after its JupyterKernel is dropped.
+*/
+use std::collections::HashMap;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast;
+
+use crate::kernels::limits::{ProcessGroup, ResourceLimits};
+use crate::kernels::logs::{KernelLogs, LogSource};
+use crate::kernels::options::KernelLaunchOptions;
+use crate::kernels::{KernelDied, KernelEvent};
+
+// How the Kernel process exited, and how much CPU time it used (None where we can't tell)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Exit {
+    pub(crate) status: ExitStatus,
+    pub(crate) cpu_time: Option<Duration>,
+}
+
+// The Kernel's Child, reaped with wait4 instead of Child::try_wait so the exit comes with the
+// child's rusage. Once reaped the pid may be reused, so it's never signalled again.
+#[derive(Debug)]
+struct KernelChild {
+    child: Child,
+    exit: Option<Exit>,
+}
+
+impl KernelChild {
+    fn new(child: Child) -> Self {
+        Self { child, exit: None }
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<Exit>> {
+        self.reap(false)
+    }
+
+    fn wait(&mut self) -> std::io::Result<Exit> {
+        loop {
+            if let Some(exit) = self.reap(true)? {
+                return Ok(exit);
+            }
+        }
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        match self.exit {
+            Some(_) => Ok(()),
+            None => self.child.kill(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn reap(&mut self, block: bool) -> std::io::Result<Option<Exit>> {
+        use std::os::unix::process::ExitStatusExt;
+        if self.exit.is_some() {
+            return Ok(self.exit);
+        }
+        let flags = if block { 0 } else { libc::WNOHANG };
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let pid = unsafe {
+            libc::wait4(
+                self.child.id() as libc::pid_t,
+                &mut status,
+                flags,
+                &mut usage,
+            )
+        };
+        if pid < 0 {
+            let err = std::io::Error::last_os_error();
+            return match err.kind() {
+                std::io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(err),
+            };
+        }
+        if pid == 0 {
+            return Ok(None);
+        }
+        let timeval = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+        self.exit = Some(Exit {
+            status: ExitStatus::from_raw(status),
+            cpu_time: Some(timeval(usage.ru_utime) + timeval(usage.ru_stime)),
+        });
+        Ok(self.exit)
+    }
+
+    #[cfg(not(unix))]
+    fn reap(&mut self, block: bool) -> std::io::Result<Option<Exit>> {
+        if self.exit.is_none() {
+            let status = match block {
+                true => Some(self.child.wait()?),
+                false => self.child.try_wait()?,
+            };
+            self.exit = status.map(|status| Exit {
+                status,
+                cpu_time: None,
+            });
+        }
+        Ok(self.exit)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct KernelProcess {
+    child: Mutex<KernelChild>,
+    pid: AtomicU32,
+    // fully formatted argv (connection file filled in) and env, kept around for restarts
+    cmd: Vec<String>,
+    spec_env: HashMap<String, String>,
+    options: KernelLaunchOptions,
+    pub(crate) logs: KernelLogs,
+    // threads reading the current process' stdout/stderr into logs
+    readers: Mutex<Vec<JoinHandle<()>>>,
+    pub(crate) events: broadcast::Sender<KernelEvent>,
+    shutting_down: AtomicBool,
+    // one restart at a time, and set while the old process is being replaced
+    restart_lock: Mutex<()>,
+    restarting: AtomicBool,
+    // bumped on every restart so exit watchers for an old process know to stop
+    generation: AtomicU64,
+}
+
+impl KernelProcess {
+    // Kernel stdout and stderr are always captured into the log buffer. silent controls whether
+    // they're also echoed to this process' stdout, stderr is echoed either way.
+    fn spawn_child(
+        cmd: &[String],
+        spec_env: &HashMap<String, String>,
+        options: &KernelLaunchOptions,
+        logs: &KernelLogs,
+    ) -> (Child, Vec<JoinHandle<()>>) {
+        let mut process = options
+            .command(cmd, spec_env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to start Jupyter Kernel");
+        let mut readers = vec![];
+        if let Some(stdout) = process.stdout.take() {
+            readers.push(logs.capture(stdout, LogSource::Stdout, !options.silent));
+        }
+        if let Some(stderr) = process.stderr.take() {
+            readers.push(logs.capture(stderr, LogSource::Stderr, true));
+        }
+        (process, readers)
+    }
+
+    pub(crate) fn spawn(
+        cmd: Vec<String>,
+        spec_env: HashMap<String, String>,
+        options: KernelLaunchOptions,
+    ) -> Arc<Self> {
+        let logs = KernelLogs::default();
+        let (child, readers) = Self::spawn_child(&cmd, &spec_env, &options, &logs);
+        let (events, _) = broadcast::channel(16);
+        let process = Arc::new(Self {
+            pid: AtomicU32::new(child.id()),
+            child: Mutex::new(KernelChild::new(child)),
+            cmd,
+            spec_env,
+            options,
+            logs,
+            readers: Mutex::new(readers),
+            events,
+            shutting_down: AtomicBool::new(false),
+            restart_lock: Mutex::new(()),
+            restarting: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+        });
+        process.watch_exit();
+        process
+    }
+
+    pub(crate) fn pid(&self) -> u32 {
+        self.pid.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn limits(&self) -> &ResourceLimits {
+        &self.options.limits
+    }
+
+    // None while a restart is swapping the process out, the old one exiting isn't a death
+    pub(crate) fn try_wait(&self) -> Option<Exit> {
+        if self.restarting.load(Ordering::SeqCst) {
+            return None;
+        }
+        self.child.lock().unwrap().try_wait().ok().flatten()
+    }
+
+    // Once the process has exited, give the log reader threads a chance to drain the pipes so its
+    // last words (usually the traceback saying why it died) are in the buffer. Bounded, since
+    // anything the Kernel started can keep the pipes open after it's gone.
+    pub(crate) fn drain_logs(&self) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            let drained = self
+                .readers
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|reader| reader.is_finished());
+            if drained || Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // Why the process exited, once its last log lines are in
+    pub(crate) fn died(&self, exit: Exit) -> KernelDied {
+        self.drain_logs();
+        let logs = self.logs.lines();
+        let status = exit.status;
+        let limit_exceeded = self.limits().exceeded(&status, exit.cpu_time);
+        let likely_cause = match limit_exceeded {
+            Some(_) => None,
+            None => self.limits().likely_cause(&status, &logs),
+        };
+        KernelDied {
+            status,
+            logs,
+            limit_exceeded,
+            likely_cause,
+        }
+    }
+
+    // Background thread that notices when the Kernel process exits and publishes a KernelEvent.
+    // Exits caused by us killing the Kernel (drop, restart) aren't reported.
+    fn watch_exit(self: &Arc<Self>) {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let process = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(100));
+            let process = match process.upgrade() {
+                Some(process) => process,
+                None => break,
+            };
+            let exit = {
+                let mut child = process.child.lock().unwrap();
+                if process.shutting_down.load(Ordering::SeqCst)
+                    || process.generation.load(Ordering::SeqCst) != generation
+                {
+                    break;
+                }
+                match child.try_wait() {
+                    Ok(Some(exit)) => exit,
+                    Ok(None) => continue,
+                    Err(_) => break,
+                }
+            };
+            // Only proven limits get their own event, likely_cause is left for KernelDied
+            let status = exit.status;
+            let event = match process.limits().exceeded(&status, exit.cpu_time) {
+                Some(limit) => KernelEvent::ResourceLimitExceeded { limit, status },
+                None => KernelEvent::Exited(status),
+            };
+            // Err just means nobody is subscribed
+            let _ = process.events.send(event);
+            break;
+        });
+    }
+
+    // Send a signal to the Kernel, or its whole process group if it has one
+    #[cfg(unix)]
+    fn signal(&self, child: &mut KernelChild, signal: libc::c_int) {
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        let pid = self.pid() as libc::pid_t;
+        unsafe {
+            match self.options.process_group {
+                ProcessGroup::Inherit => libc::kill(pid, signal),
+                ProcessGroup::NewGroup | ProcessGroup::NewSession => libc::killpg(pid, signal),
+            };
+        }
+    }
+
+    // Like hitting ctrl-c in a terminal, Kernels turn SIGINT into a KeyboardInterrupt (or the
+    // language equivalent) for whatever code is running
+    pub(crate) fn interrupt(&self) {
+        #[cfg(unix)]
+        {
+            let mut child = self.child.lock().unwrap();
+            self.signal(&mut child, libc::SIGINT);
+        }
+    }
+
+    fn kill_child(&self, child: &mut KernelChild) {
+        #[cfg(unix)]
+        self.signal(child, libc::SIGKILL);
+        child.kill().expect("Failed to kill Kernel process");
+        let _ = child.wait();
+    }
+
+    // Kill the Kernel and start it again with the same command and connection file, so Clients
+    // can reconnect on the same ports. The child lock is only held for the kill and the swap, so
+    // interrupt and try_wait don't block on the old process exiting or the new one spawning.
+    pub(crate) fn restart(self: &Arc<Self>) {
+        let _restart_lock = self.restart_lock.lock().unwrap();
+        self.restarting.store(true, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        {
+            let mut child = self.child.lock().unwrap();
+            #[cfg(unix)]
+            self.signal(&mut child, libc::SIGKILL);
+            child.kill().expect("Failed to kill Kernel process");
+        }
+        while let Ok(None) = self.child.lock().unwrap().try_wait() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let (new_child, readers) =
+            Self::spawn_child(&self.cmd, &self.spec_env, &self.options, &self.logs);
+        {
+            let mut child = self.child.lock().unwrap();
+            self.pid.store(new_child.id(), Ordering::SeqCst);
+            *child = KernelChild::new(new_child);
+            *self.readers.lock().unwrap() = readers;
+        }
+        self.restarting.store(false, Ordering::SeqCst);
+        self.watch_exit();
+        let _ = self.events.send(KernelEvent::Restarted);
+    }
+
+    pub(crate) fn kill(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let mut child = self.child.lock().unwrap();
+        self.kill_child(&mut child);
+    }
+}
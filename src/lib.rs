@@ -1,6 +1,10 @@
 pub mod actions;
 pub mod client;
+pub mod execution_queue;
+pub mod executor;
 pub mod handlers;
+pub mod heartbeat;
 pub mod jupyter;
 pub mod kernels;
 pub mod notebook;
+pub mod session;
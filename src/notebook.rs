@@ -2,26 +2,70 @@
 Models a Notebook document. https://ipython.org/ipython-doc/3/notebook/nbformat.html
 */
 
-use crate::jupyter::iopub_content::display_data::DisplayData;
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::jupyter::iopub_content::display_data::{DisplayData, UpdateDisplayData};
 use crate::jupyter::iopub_content::errors::Error;
 use crate::jupyter::iopub_content::execute_result::ExecuteResult;
-use crate::jupyter::iopub_content::stream::Stream;
+use crate::jupyter::iopub_content::stream::{Stream, StreamName};
+use crate::jupyter::response::Response;
 use enum_as_inner::EnumAsInner;
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "NotebookFields")]
 pub struct Notebook {
     pub cells: Vec<Cell>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
-    #[serde(
-        serialize_with = "serialize_json_value_as_empty_object",
-        deserialize_with = "serde_json::value::Value::deserialize"
-    )]
+    #[serde(serialize_with = "serialize_json_value_as_empty_object")]
     pub metadata: serde_json::Value,
     pub nbformat: u32,
     pub nbformat_minor: u32,
+    // display_id -> ids of cells that have shown it, see update_display_data
+    #[serde(skip)]
+    display_index: HashMap<String, HashSet<String>>,
+}
+
+// What's actually in an .ipynb file. Deserializing goes through this so the display index is
+// always built from the outputs that were loaded.
+#[derive(Deserialize)]
+struct NotebookFields {
+    cells: Vec<Cell>,
+    signature: Option<String>,
+    #[serde(deserialize_with = "serde_json::value::Value::deserialize")]
+    metadata: serde_json::Value,
+    nbformat: u32,
+    nbformat_minor: u32,
+}
+
+impl From<NotebookFields> for Notebook {
+    fn from(fields: NotebookFields) -> Self {
+        let mut nb = Self {
+            cells: fields.cells,
+            signature: fields.signature,
+            metadata: fields.metadata,
+            nbformat: fields.nbformat,
+            nbformat_minor: fields.nbformat_minor,
+            display_index: HashMap::new(),
+        };
+        nb.rebuild_display_index();
+        nb
+    }
+}
+
+// The display index is derived from the cells, so it's left out
+impl PartialEq for Notebook {
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+            && self.signature == other.signature
+            && self.metadata == other.metadata
+            && self.nbformat == other.nbformat
+            && self.nbformat_minor == other.nbformat_minor
+    }
 }
 
 impl Default for Notebook {
@@ -38,6 +82,7 @@ impl Notebook {
             metadata: serde_json::Value::Null,
             nbformat: 4,
             nbformat_minor: 5,
+            display_index: HashMap::new(),
         }
     }
 
@@ -79,6 +124,56 @@ impl Notebook {
         cell
     }
 
+    // Remember that a cell is showing display_data with this display_id (display(..., display_id=..)
+    // and progress bars like tqdm), so later update_display_data messages can find it
+    pub fn track_display(&mut self, display_id: &str, cell_id: &str) {
+        self.display_index
+            .entry(display_id.to_string())
+            .or_default()
+            .insert(cell_id.to_string());
+    }
+
+    // Index display_ids of outputs that are already in the cells. Deserializing does this, it's only
+    // needed after changing cells directly.
+    pub fn rebuild_display_index(&mut self) {
+        self.display_index.clear();
+        let mut found = vec![];
+        for cell in &self.cells {
+            if let Cell::Code(code) = cell {
+                for output in &code.outputs {
+                    if let Some(transient) =
+                        output.as_display_data().and_then(|d| d.transient.as_ref())
+                    {
+                        found.push((transient.display_id.clone(), code.id.clone()));
+                    }
+                }
+            }
+        }
+        for (display_id, cell_id) in found {
+            self.track_display(&display_id, &cell_id);
+        }
+    }
+
+    // Replace data and metadata of every display_data output with this display_id, in whichever
+    // cells hold one, including cells from earlier executions. Returns how many outputs changed.
+    pub fn update_display_data(&mut self, update: &UpdateDisplayData) -> usize {
+        let display_id = match &update.transient {
+            Some(transient) => &transient.display_id,
+            None => return 0,
+        };
+        let cell_ids = match self.display_index.get(display_id) {
+            Some(cell_ids) => cell_ids.clone(),
+            None => return 0,
+        };
+        let mut updated = 0;
+        for cell_id in cell_ids {
+            if let Some(Cell::Code(cell)) = self.get_mut_cell(&cell_id) {
+                updated += cell.update_display_data(display_id, update);
+            }
+        }
+        updated
+    }
+
     pub fn add_markdown_cell(&mut self, source: &str) -> Cell {
         let cell = Cell::Markdown(MarkdownCell {
             id: uuid::Uuid::new_v4().to_string(),
@@ -94,11 +189,118 @@ impl Notebook {
 #[serde(tag = "output_type", rename_all = "snake_case")]
 pub enum Output {
     DisplayData(DisplayData),
-    Stream(Stream),
+    Stream(StreamOutput),
     ExecuteResult(ExecuteResult),
     Error(Error),
 }
 
+impl Output {
+    // The Output a Kernel message adds to a cell, None for messages that don't produce one
+    pub fn from_response(msg: &Response) -> Option<Self> {
+        match msg {
+            Response::DisplayData(m) => Some(Output::DisplayData(m.content.clone())),
+            Response::Stream(m) => Some(Output::Stream(m.content.clone().into())),
+            Response::ExecuteResult(m) => Some(Output::ExecuteResult(m.content.clone())),
+            Response::Error(m) => Some(Output::Error(m.content.clone())),
+            _ => None,
+        }
+    }
+}
+
+// A stream output in a cell. Unlike the iopub Stream it's made from, it also remembers where the
+// next character lands on the unfinished last line of text when a \r or \b moved the cursor back
+// from the end, see append_output. The cursor is never saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamOutput {
+    pub name: StreamName,
+    #[serde(deserialize_with = "list_or_string_to_string")]
+    pub text: String,
+    #[serde(skip)]
+    cursor: Option<usize>,
+}
+
+impl From<Stream> for StreamOutput {
+    fn from(stream: Stream) -> Self {
+        Self {
+            name: stream.name,
+            text: stream.text,
+            cursor: None,
+        }
+    }
+}
+
+// The cursor isn't part of the output, two streams showing the same text are the same
+impl PartialEq for StreamOutput {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.text == other.text
+    }
+}
+
+// Add an output to a list the way nbformat and JupyterLab do: a stream right after another stream
+// with the same name is merged into it, and carriage returns / backspaces are applied so the text
+// is what a terminal would show (e.g. only the latest state of a progress bar)
+pub fn append_output(outputs: &mut Vec<Output>, output: Output) {
+    let mut stream = match output {
+        Output::Stream(stream) => stream,
+        output => {
+            outputs.push(output);
+            return;
+        }
+    };
+    if let Some(Output::Stream(last)) = outputs.last_mut() {
+        if last.name == stream.name {
+            // Control characters never reach back past a newline, so only the last line of what's
+            // already there needs another pass
+            let line_start = last.text.rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let (line, cursor) =
+                apply_terminal_controls(&last.text[line_start..], last.cursor, &stream.text);
+            last.text.truncate(line_start);
+            last.text.push_str(&line);
+            last.cursor = cursor;
+            return;
+        }
+    }
+    let (text, cursor) = apply_terminal_controls("", None, &stream.text);
+    stream.text = text;
+    stream.cursor = cursor;
+    outputs.push(Output::Stream(stream));
+}
+
+// Apply \r (back to the start of the line) and \b (back one character) in text written after line,
+// an unfinished line with the cursor at cursor (its end if None). Later characters overwrite
+// earlier ones. Also returns where the cursor ended up if that's not the end of the last line, so
+// the next chunk of the stream overwrites the right characters.
+fn apply_terminal_controls(
+    line: &str,
+    cursor: Option<usize>,
+    text: &str,
+) -> (String, Option<usize>) {
+    let mut result = String::with_capacity(line.len() + text.len());
+    let mut line: Vec<char> = line.chars().collect();
+    let mut cursor = cursor.unwrap_or(line.len()).min(line.len());
+    for c in text.chars() {
+        match c {
+            '\n' => {
+                result.extend(line.drain(..));
+                result.push('\n');
+                cursor = 0;
+            }
+            '\r' => cursor = 0,
+            '\u{8}' => cursor = cursor.saturating_sub(1),
+            c => {
+                if cursor < line.len() {
+                    line[cursor] = c;
+                } else {
+                    line.push(c);
+                }
+                cursor += 1;
+            }
+        }
+    }
+    result.extend(line.iter());
+    (result, (cursor < line.len()).then_some(cursor))
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "cell_type", rename_all = "lowercase")]
 pub enum Cell {
@@ -151,6 +353,24 @@ impl Cell {
             cell.clear_output();
         }
     }
+
+    pub fn set_execution_count(&mut self, execution_count: Option<u32>) {
+        if let Cell::Code(cell) = self {
+            cell.execution_count = execution_count;
+        }
+    }
+
+    pub fn record_timing(&mut self, key: &str, date: DateTime<Utc>) {
+        if let Cell::Code(cell) = self {
+            cell.record_timing(key, date);
+        }
+    }
+
+    pub fn clear_timing(&mut self) {
+        if let Cell::Code(cell) = self {
+            cell.clear_timing();
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -170,12 +390,54 @@ pub struct CodeCell {
 
 impl CodeCell {
     pub fn add_output(&mut self, output: Output) {
-        self.outputs.push(output);
+        append_output(&mut self.outputs, output);
     }
 
     pub fn clear_output(&mut self) {
         self.outputs = vec![];
     }
+
+    // Set a JupyterLab-style metadata.execution timestamp, e.g. "iopub.execute_input"
+    pub fn record_timing(&mut self, key: &str, date: DateTime<Utc>) {
+        if !self.metadata.is_object() {
+            self.metadata = serde_json::json!({});
+        }
+        let execution = self.metadata["execution"].take();
+        let mut execution = match execution {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        // Same format JupyterLab writes
+        let date = date.to_rfc3339_opts(SecondsFormat::Micros, true);
+        execution.insert(key.to_string(), date.into());
+        self.metadata["execution"] = execution.into();
+    }
+
+    // Drop timestamps from an earlier run
+    pub fn clear_timing(&mut self) {
+        if let Some(metadata) = self.metadata.as_object_mut() {
+            metadata.remove("execution");
+        }
+    }
+
+    // Returns how many outputs were updated
+    fn update_display_data(&mut self, display_id: &str, update: &UpdateDisplayData) -> usize {
+        let mut updated = 0;
+        for output in self.outputs.iter_mut() {
+            if let Output::DisplayData(display) = output {
+                let matches = display
+                    .transient
+                    .as_ref()
+                    .is_some_and(|t| t.display_id == display_id);
+                if matches {
+                    display.data = update.data.clone();
+                    display.metadata = update.metadata.clone();
+                    updated += 1;
+                }
+            }
+        }
+        updated
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -0,0 +1,164 @@
+/*
+NotebookSession runs the code cells of a Notebook on a Kernel, instead of wiring up OutputHandlers
+and execute_requests by hand.
+
+let nb = Arc::new(Mutex::new(Notebook::from_file("analysis.ipynb")));
+let session = NotebookSession::new(nb.clone(), client);
+let runs = session.run_all().await;
+nb.lock().await.save("analysis.ipynb");
+
+Each cell that runs has its old outputs and execution_count cleared, then an OutputHandler fills
+them back in. Runs of several cells go through an ExecutionQueue, by default with
+QueueMode::HaltOnError: cells are sent one at a time and the ones after a failing cell are never
+sent, so they come back ExecutionResult::Aborted like in JupyterLab's "Run All".
+*/
+use std::fmt;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::client::Client;
+use crate::execution_queue::{ExecutionQueue, ExecutionResult, QueueMode};
+use crate::handlers::outputs::OutputHandler;
+use crate::handlers::HandlerSet;
+use crate::notebook::{Cell, Notebook};
+
+#[derive(Debug)]
+pub enum RunCellError {
+    CellNotFound(String),
+    // Only code cells can be run
+    NotCodeCell(String),
+}
+
+impl fmt::Display for RunCellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunCellError::CellNotFound(id) => write!(f, "Cell {} not found in Notebook", id),
+            RunCellError::NotCodeCell(id) => write!(f, "Cell {} is not a code cell", id),
+        }
+    }
+}
+
+impl std::error::Error for RunCellError {}
+
+// How one cell went, see NotebookSession::run_all
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellRun {
+    pub cell_id: String,
+    pub result: ExecutionResult,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotebookSession {
+    nb: Arc<Mutex<Notebook>>,
+    client: Client,
+    mode: QueueMode,
+    record_timing: bool,
+}
+
+impl NotebookSession {
+    pub fn new(nb: Arc<Mutex<Notebook>>, client: Client) -> Self {
+        Self {
+            nb,
+            client,
+            mode: QueueMode::HaltOnError,
+            record_timing: false,
+        }
+    }
+
+    // Whether cells keep running after one fails when running several at once
+    pub fn queue_mode(mut self, mode: QueueMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // Write JupyterLab-style metadata.execution timestamps, see OutputHandler::with_timing
+    pub fn with_timing(mut self) -> Self {
+        self.record_timing = true;
+        self
+    }
+
+    pub fn notebook(&self) -> Arc<Mutex<Notebook>> {
+        self.nb.clone()
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn run_cell(&self, id: &str) -> Result<ExecutionResult, RunCellError> {
+        // Checked and queued under one lock, so the cell can't go away in between
+        let queue = {
+            let mut nb = self.nb.lock().await;
+            match nb.get_cell(id) {
+                Some(Cell::Code(_)) => {}
+                Some(_) => return Err(RunCellError::NotCodeCell(id.to_string())),
+                None => return Err(RunCellError::CellNotFound(id.to_string())),
+            }
+            self.queue_cells(&mut nb, vec![id.to_string()]).0
+        };
+        let mut results = queue.run().await;
+        Ok(results.remove(0))
+    }
+
+    // Every code cell, top to bottom
+    pub async fn run_all(&self) -> Vec<CellRun> {
+        let ids = self.cell_ids().await;
+        self.run_cells(ids).await
+    }
+
+    // Code cells above this one, not including it
+    pub async fn run_above(&self, id: &str) -> Result<Vec<CellRun>, RunCellError> {
+        let mut ids = self.cell_ids().await;
+        ids.truncate(self.position(&ids, id)?);
+        Ok(self.run_cells(ids).await)
+    }
+
+    // This cell and every code cell below it
+    pub async fn run_below(&self, id: &str) -> Result<Vec<CellRun>, RunCellError> {
+        let mut ids = self.cell_ids().await;
+        let ids = ids.split_off(self.position(&ids, id)?);
+        Ok(self.run_cells(ids).await)
+    }
+
+    async fn cell_ids(&self) -> Vec<String> {
+        let nb = self.nb.lock().await;
+        nb.cells.iter().map(|cell| cell.id().to_string()).collect()
+    }
+
+    fn position(&self, ids: &[String], id: &str) -> Result<usize, RunCellError> {
+        ids.iter()
+            .position(|cell_id| cell_id == id)
+            .ok_or_else(|| RunCellError::CellNotFound(id.to_string()))
+    }
+
+    // Non-code cells in ids are skipped
+    async fn run_cells(&self, ids: Vec<String>) -> Vec<CellRun> {
+        let (queue, queued) = self.queue_cells(&mut *self.nb.lock().await, ids);
+        let results = queue.run().await;
+        queued
+            .into_iter()
+            .zip(results)
+            .map(|(cell_id, result)| CellRun { cell_id, result })
+            .collect()
+    }
+
+    // Clear the code cells in ids and queue them up, along with the ids that made it in
+    fn queue_cells(&self, nb: &mut Notebook, ids: Vec<String>) -> (ExecutionQueue, Vec<String>) {
+        let mut queue = self.client.execution_queue(self.mode);
+        let mut queued = vec![];
+        for id in ids {
+            if let Some(Cell::Code(cell)) = nb.get_mut_cell(&id) {
+                cell.clear_output();
+                cell.execution_count = None;
+                let mut handler = OutputHandler::new(self.nb.clone(), &id);
+                if self.record_timing {
+                    handler = handler.with_timing();
+                }
+                queue = queue.push(&cell.source, HandlerSet::new().with(handler));
+                queued.push(id);
+            }
+        }
+        (queue, queued)
+    }
+}
@@ -0,0 +1,320 @@
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use serde_json::json;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use zeromq::{PubSocket, RepSocket, RouterSocket, Socket, SocketRecv, SocketSend, ZmqMessage};
+
+// Stand-in for the Kernel side of the ZMQ channels. Requests are never answered, so Actions stay
+// pending until something else finishes them.
+// Only held so the sockets stay bound
+pub struct FakeChannels {
+    _iopub: PubSocket,
+    _shell: RouterSocket,
+    _control: RouterSocket,
+}
+
+pub async fn bind_channels(connection_info: &ConnectionInfo) -> FakeChannels {
+    let mut iopub = PubSocket::new();
+    iopub.bind(&connection_info.iopub_address()).await.unwrap();
+    let mut shell = RouterSocket::new();
+    shell.bind(&connection_info.shell_address()).await.unwrap();
+    let mut control = RouterSocket::new();
+    control
+        .bind(&connection_info.control_address())
+        .await
+        .unwrap();
+    FakeChannels {
+        _iopub: iopub,
+        _shell: shell,
+        _control: control,
+    }
+}
+
+// Echo pings back on the heartbeat channel until aborted
+pub async fn serve_heartbeat(connection_info: &ConnectionInfo) -> JoinHandle<()> {
+    let mut socket = RepSocket::new();
+    socket
+        .bind(&connection_info.heartbeat_address())
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        while let Ok(msg) = socket.recv().await {
+            socket.send(msg).await.unwrap();
+        }
+    })
+}
+
+// A request the FakeKernel received on shell or control
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub msg_id: String,
+    pub msg_type: String,
+    pub content: serde_json::Value,
+    // The execute_request that was running when this arrived
+    pub executing: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    received: Vec<Received>,
+    executing: Option<String>,
+    execution_count: u32,
+}
+
+// Kernel that answers kernel_info_request and execute_request over real ZMQ sockets, one request
+// at a time like a real Kernel. What an execute_request does depends on its code:
+//   "sleep <secs>"        busy for that long, or until an interrupt_request arrives on control
+//   "raise"               replies with an error
+//   "reply_status <s>"    replies with status <s>
+//   anything else         replies ok
+// stop_on_error is ignored, like evcxr and IRkernel do.
+pub struct FakeKernel {
+    state: Arc<Mutex<FakeState>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl FakeKernel {
+    pub async fn start(connection_info: &ConnectionInfo) -> Self {
+        let mut iopub = PubSocket::new();
+        iopub.bind(&connection_info.iopub_address()).await.unwrap();
+        let mut shell = RouterSocket::new();
+        shell.bind(&connection_info.shell_address()).await.unwrap();
+        let mut control = RouterSocket::new();
+        control
+            .bind(&connection_info.control_address())
+            .await
+            .unwrap();
+
+        let state = Arc::new(Mutex::new(FakeState::default()));
+        let interrupt = Arc::new(Notify::new());
+        let (iopub_tx, mut iopub_rx) = mpsc::unbounded_channel::<ZmqMessage>();
+        let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<ZmqMessage>();
+        let (request_tx, mut request_rx) = mpsc::unbounded_channel::<ZmqMessage>();
+
+        let iopub_task = tokio::spawn(async move {
+            while let Some(msg) = iopub_rx.recv().await {
+                let _ = iopub.send(msg).await;
+            }
+        });
+        let shell_task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    msg = shell.recv() => match msg {
+                        Ok(msg) => {
+                            let _ = request_tx.send(msg);
+                        }
+                        Err(_) => break,
+                    },
+                    Some(reply) = reply_rx.recv() => {
+                        let _ = shell.send(reply).await;
+                    }
+                }
+            }
+        });
+        let worker_state = state.clone();
+        let worker_interrupt = interrupt.clone();
+        let worker_task = tokio::spawn(async move {
+            while let Some(msg) = request_rx.recv().await {
+                let request = Incoming::parse(msg);
+                let received = request.received(&worker_state);
+                let publish = |msg_type: &str, content: serde_json::Value| {
+                    let _ = iopub_tx.send(request.reply(Bytes::from("kernel"), msg_type, content));
+                };
+                publish("status", json!({"execution_state": "busy"}));
+                let (reply_type, content) = match received.msg_type.as_str() {
+                    "kernel_info_request" => ("kernel_info_reply", kernel_info()),
+                    "execute_request" => {
+                        let count = {
+                            let mut state = worker_state.lock().unwrap();
+                            state.executing = Some(received.msg_id.clone());
+                            state.execution_count += 1;
+                            state.execution_count
+                        };
+                        let code = received.content["code"].as_str().unwrap_or("").to_string();
+                        publish(
+                            "execute_input",
+                            json!({"code": code, "execution_count": count}),
+                        );
+                        let content = execute(&code, count, &worker_interrupt).await;
+                        worker_state.lock().unwrap().executing = None;
+                        ("execute_reply", content)
+                    }
+                    other => (other, json!({})),
+                };
+                let _ = reply_tx.send(request.reply(request.identity.clone(), reply_type, content));
+                publish("status", json!({"execution_state": "idle"}));
+            }
+        });
+        let control_state = state.clone();
+        let control_task = tokio::spawn(async move {
+            while let Ok(msg) = control.recv().await {
+                let request = Incoming::parse(msg);
+                let received = request.received(&control_state);
+                if received.msg_type == "interrupt_request" {
+                    interrupt.notify_waiters();
+                    let reply = request.reply(
+                        request.identity.clone(),
+                        "interrupt_reply",
+                        json!({"status": "ok"}),
+                    );
+                    let _ = control.send(reply).await;
+                }
+            }
+        });
+        let heartbeat_task = serve_heartbeat(connection_info).await;
+        Self {
+            state,
+            tasks: vec![
+                iopub_task,
+                shell_task,
+                worker_task,
+                control_task,
+                heartbeat_task,
+            ],
+        }
+    }
+
+    // Everything received so far with this msg_type, in order
+    pub fn received(&self, msg_type: &str) -> Vec<Received> {
+        self.state
+            .lock()
+            .unwrap()
+            .received
+            .iter()
+            .filter(|received| received.msg_type == msg_type)
+            .cloned()
+            .collect()
+    }
+
+    // Wait up to a few seconds for at least count requests of this msg_type
+    pub async fn wait_for(&self, msg_type: &str, count: usize) -> Vec<Received> {
+        for _ in 0..100 {
+            let received = self.received(msg_type);
+            if received.len() >= count {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("Timed out waiting for {} {}", count, msg_type);
+    }
+}
+
+impl Drop for FakeKernel {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+// A request off a ROUTER socket, frames are [identity, <IDS|MSG>, signature, header, parent_header,
+// metadata, content]
+struct Incoming {
+    identity: Bytes,
+    header: Bytes,
+    msg_id: String,
+    msg_type: String,
+    content: serde_json::Value,
+}
+
+impl Incoming {
+    fn parse(msg: ZmqMessage) -> Self {
+        let frames = msg.into_vec();
+        let delimiter = frames
+            .iter()
+            .position(|frame| frame.as_ref() == b"<IDS|MSG>")
+            .expect("Missing delimiter frame");
+        let header = frames[delimiter + 2].clone();
+        let parsed: serde_json::Value = serde_json::from_slice(&header).unwrap();
+        Self {
+            identity: frames[0].clone(),
+            msg_id: parsed["msg_id"].as_str().unwrap().to_string(),
+            msg_type: parsed["msg_type"].as_str().unwrap().to_string(),
+            content: serde_json::from_slice(&frames[delimiter + 5]).unwrap(),
+            header,
+        }
+    }
+
+    fn received(&self, state: &Mutex<FakeState>) -> Received {
+        let mut state = state.lock().unwrap();
+        let received = Received {
+            msg_id: self.msg_id.clone(),
+            msg_type: self.msg_type.clone(),
+            content: self.content.clone(),
+            executing: state.executing.clone(),
+        };
+        state.received.push(received.clone());
+        received
+    }
+
+    // first is the ROUTER identity for replies, or the topic for iopub. The signature isn't
+    // checked by Client so it's left empty.
+    fn reply(&self, first: Bytes, msg_type: &str, content: serde_json::Value) -> ZmqMessage {
+        let header = json!({
+            "msg_id": uuid::Uuid::new_v4().to_string(),
+            "session": "fake-kernel",
+            "username": "fake-kernel",
+            "date": chrono::Utc::now(),
+            "msg_type": msg_type,
+            "version": "5.3",
+        });
+        let frames = vec![
+            first,
+            Bytes::from("<IDS|MSG>"),
+            Bytes::new(),
+            Bytes::from(header.to_string()),
+            self.header.clone(),
+            Bytes::from("{}"),
+            Bytes::from(content.to_string()),
+        ];
+        ZmqMessage::try_from(frames).unwrap()
+    }
+}
+
+async fn execute(code: &str, count: u32, interrupt: &Notify) -> serde_json::Value {
+    let error = |ename: &str| {
+        json!({
+            "status": "error",
+            "execution_count": count,
+            "ename": ename,
+            "evalue": "",
+            "traceback": [],
+        })
+    };
+    if let Some(secs) = code.strip_prefix("sleep ") {
+        let secs: f64 = secs.parse().unwrap();
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs_f64(secs)) => {}
+            _ = interrupt.notified() => return error("KeyboardInterrupt"),
+        }
+    } else if code == "raise" {
+        return error("ValueError");
+    } else if let Some(status) = code.strip_prefix("reply_status ") {
+        return json!({"status": status, "execution_count": count});
+    }
+    json!({"status": "ok", "execution_count": count})
+}
+
+fn kernel_info() -> serde_json::Value {
+    json!({
+        "status": "ok",
+        "protocol_version": "5.3",
+        "implementation": "fake",
+        "implementation_version": "0.1",
+        "banner": "",
+        "help_links": [],
+        "language_info": {
+            "name": "python",
+            "version": "3",
+            "mimetype": "text/x-python",
+            "file_extension": ".py",
+        },
+    })
+}
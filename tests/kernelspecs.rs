@@ -0,0 +1,69 @@
+// Not every test file uses every kernelspec
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Kernelspecs for tests that don't need a real Kernel, installed once into a temp dir that's put on
+// JUPYTER_PATH. JUPYTER_PATH is process wide, so every test in a binary shares the same one.
+//   crashes             exits with an error on startup
+//   message-interrupt   stays up doing nothing, with interrupt_mode "message"
+//   hands-off           stays up doing nothing, after copying its connection file into
+//                       handed_off_dir() for a FakeKernel to serve
+pub fn install() -> PathBuf {
+    static JUPYTER_PATH: OnceLock<PathBuf> = OnceLock::new();
+    JUPYTER_PATH
+        .get_or_init(|| {
+            let jupyter_path =
+                std::env::temp_dir().join(format!("ks-path-{}", uuid::Uuid::new_v4()));
+            write_spec(
+                &jupyter_path,
+                "crashes",
+                serde_json::json!({
+                    "argv": ["sh", "-c", "echo 'No module named ipykernel' >&2; exit 1", "{connection_file}"],
+                    "display_name": "Crashes",
+                    "language": "python",
+                }),
+            );
+            write_spec(
+                &jupyter_path,
+                "message-interrupt",
+                serde_json::json!({
+                    "argv": ["sh", "-c", "sleep 60", "{connection_file}"],
+                    "display_name": "Message Interrupt",
+                    "language": "python",
+                    "interrupt_mode": "message",
+                }),
+            );
+            let handed_off = jupyter_path.join("handed-off");
+            std::fs::create_dir_all(&handed_off).unwrap();
+            // Copied under a temporary name first so nobody reads a half written file
+            let script = format!(
+                "cp \"$0\" {dir}/.$$ && mv {dir}/.$$ {dir}/$$.json && exec sleep 60",
+                dir = handed_off.display()
+            );
+            write_spec(
+                &jupyter_path,
+                "hands-off",
+                serde_json::json!({
+                    "argv": ["sh", "-c", script, "{connection_file}"],
+                    "display_name": "Hands Off",
+                    "language": "python",
+                }),
+            );
+            std::env::set_var("JUPYTER_PATH", &jupyter_path);
+            jupyter_path
+        })
+        .clone()
+}
+
+// Where hands-off Kernels leave their connection files
+pub fn handed_off_dir() -> PathBuf {
+    install().join("handed-off")
+}
+
+fn write_spec(jupyter_path: &Path, name: &str, kernel_json: serde_json::Value) {
+    let resource_dir = jupyter_path.join("kernels").join(name);
+    std::fs::create_dir_all(&resource_dir).unwrap();
+    std::fs::write(resource_dir.join("kernel.json"), kernel_json.to_string()).unwrap();
+}
@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use kernel_sidecar::actions::ActionStatus;
+use kernel_sidecar::client::Client;
+use kernel_sidecar::handlers::{handler_fn, HandlerContext, HandlerSet};
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::jupyter::iopub_content::status::KernelStatus;
+use kernel_sidecar::jupyter::response::Response;
+
+mod fake_kernel;
+use fake_kernel::{bind_channels, serve_heartbeat, FakeKernel};
+
+#[tokio::test]
+async fn test_cancel_queued_action() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let _heartbeat = serve_heartbeat(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client.wait_for_ready(Duration::from_secs(5)).await.unwrap();
+
+    // The fake Kernel never starts running this, cancelling resolves it anyway
+    let action = client.execute_request("2 + 2".to_string(), vec![]).await;
+    action.cancel();
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn test_cancelled_stream_ends() {
+    use tokio_stream::StreamExt;
+
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let _heartbeat = serve_heartbeat(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client.wait_for_ready(Duration::from_secs(5)).await.unwrap();
+
+    let mut stream = client.execute_request_stream("2 + 2".to_string()).await;
+    stream.action().cancel();
+    let next = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap();
+    assert!(next.is_none());
+    assert_eq!(stream.outcome().await, ActionStatus::Cancelled);
+}
+
+async fn fake_client() -> (FakeKernel, Client) {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let fake = FakeKernel::start(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+    (fake, client)
+}
+
+// Wait until the Client has seen the Kernel go busy on a request
+async fn wait_until_busy(client: &Client) {
+    let mut state = client.kernel_state();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        state.wait_for(|state| state.status == Some(KernelStatus::Busy)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_cancel_interrupts_running_request() {
+    let (fake, client) = fake_client().await;
+    let action = client.execute_request("sleep 10".to_string(), vec![]).await;
+    wait_until_busy(&client).await;
+
+    action.cancel();
+    let interrupts = fake.wait_for("interrupt_request", 1).await;
+    assert_eq!(interrupts[0].executing, Some(action.request.msg_id()));
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn test_cancel_queued_request_interrupts_it_when_it_starts() {
+    let (fake, client) = fake_client().await;
+    let running = client
+        .execute_request("sleep 0.3".to_string(), vec![])
+        .await;
+    let queued = client.execute_request("sleep 10".to_string(), vec![]).await;
+    let next = client.execute_request("x".to_string(), vec![]).await;
+    let queued_msg_id = queued.request.msg_id();
+    wait_until_busy(&client).await;
+
+    // Resolves right away, without waiting for the queued request to start
+    queued.cancel();
+    let outcome = tokio::time::timeout(Duration::from_millis(200), queued)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+    assert!(fake.received("interrupt_request").is_empty());
+
+    // Once the Kernel gets to it, it's interrupted instead of running for 10s
+    assert_eq!(running.await, ActionStatus::Completed);
+    let interrupts = fake.wait_for("interrupt_request", 1).await;
+    assert_eq!(interrupts[0].executing, Some(queued_msg_id));
+    let outcome = tokio::time::timeout(Duration::from_secs(5), next)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Completed);
+    assert_eq!(fake.received("interrupt_request").len(), 1);
+    assert_eq!(client.pending_actions().await, 0);
+}
+
+#[tokio::test]
+async fn test_dropped_action_stops_routing() {
+    let (fake, client) = fake_client().await;
+    let running = client
+        .execute_request("sleep 0.3".to_string(), vec![])
+        .await;
+    let queued = client.execute_request("x".to_string(), vec![]).await;
+    assert_eq!(client.pending_actions().await, 2);
+
+    drop(queued);
+    for _ in 0..50 {
+        if client.pending_actions().await == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(client.pending_actions().await, 1);
+    assert_eq!(running.await, ActionStatus::Completed);
+    assert!(fake.received("interrupt_request").is_empty());
+}
+
+// Pending Actions don't hold on to the Client, dropping the last clone still shuts it down
+#[tokio::test]
+async fn test_pending_action_does_not_keep_client() {
+    let (_fake, client) = fake_client().await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let handler = handler_fn(move |_msg: Response, ctx: HandlerContext| {
+        let tx = tx.clone();
+        async move {
+            let _ = tx.send(ctx.client().is_some());
+        }
+    });
+    let action = client
+        .execute_request("sleep 10".to_string(), HandlerSet::new().with(handler))
+        .await;
+    wait_until_busy(&client).await;
+    assert_eq!(client.handle_count(), 1);
+    assert_eq!(rx.recv().await, Some(true));
+
+    drop(client);
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+}
+
+#[cfg(feature = "test_ipython")]
+mod test_utils;
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_cancel_running_action() {
+    use std::sync::Arc;
+
+    use kernel_sidecar::handlers::{Handler, MessageCountHandler};
+    use tokio::sync::Mutex;
+
+    let (_kernel, client) = test_utils::start_kernel().await;
+
+    let handler = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers: Vec<Arc<Mutex<dyn Handler>>> = vec![handler.clone()];
+    let running = client
+        .execute_request("import time; time.sleep(30)".to_string(), handlers)
+        .await;
+    // Queued behind the sleep, skipped when cancelled
+    let queued = client
+        .execute_request("print('never')".to_string(), vec![])
+        .await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    queued.cancel();
+    running.cancel();
+    let outcome = tokio::time::timeout(Duration::from_secs(5), running)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+    let outcome = tokio::time::timeout(Duration::from_secs(5), queued)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Cancelled);
+    // Handlers were detached, no execute_reply made it through
+    assert!(!handler.lock().await.counts.contains_key("execute_reply"));
+
+    // And the Kernel is free for more work
+    let handler = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers: Vec<Arc<Mutex<dyn Handler>>> = vec![handler.clone()];
+    let outcome = client
+        .execute_request("2 + 2".to_string(), handlers)
+        .await
+        .await;
+    assert_eq!(outcome, ActionStatus::Completed);
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+
+// JUPYTER_RUNTIME_DIR is process-wide, so every test in this file shares one scratch runtime dir
+fn runtime_dir() -> &'static PathBuf {
+    static RUNTIME_DIR: OnceLock<PathBuf> = OnceLock::new();
+    RUNTIME_DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("ks-runtime-{}", uuid::Uuid::new_v4()));
+        std::env::set_var("JUPYTER_RUNTIME_DIR", &dir);
+        dir
+    })
+}
+
+#[test]
+fn test_tcp_addresses() {
+    let connection_info = ConnectionInfo::new(Some("test".to_string())).unwrap();
+    assert_eq!(connection_info.transport(), "tcp");
+    assert!(connection_info
+        .shell_address()
+        .starts_with("tcp://127.0.0.1:"));
+    assert!(connection_info.ipc_paths().is_empty());
+}
+
+#[test]
+fn test_ipc_addresses() {
+    let runtime_dir = runtime_dir();
+    let connection_info = ConnectionInfo::new_ipc(Some("test".to_string())).unwrap();
+    assert_eq!(connection_info.transport(), "ipc");
+
+    // ipc addresses are <path prefix>-<port> inside the runtime dir
+    let shell_address = connection_info.shell_address();
+    assert!(shell_address.starts_with(&format!("ipc://{}", runtime_dir.display())));
+    assert!(shell_address.ends_with("-1"));
+    assert_eq!(connection_info.ipc_paths().len(), 5);
+
+    // round trip through a connection file keeps the ipc settings
+    let file_path = connection_info.to_temp_file().unwrap();
+    let loaded = ConnectionInfo::from_file(&file_path).unwrap();
+    assert_eq!(
+        loaded.heartbeat_address(),
+        connection_info.heartbeat_address()
+    );
+    std::fs::remove_file(file_path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_runtime_file_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let runtime_dir = runtime_dir();
+    let connection_info = ConnectionInfo::new(Some("secure".to_string())).unwrap();
+    let file_path = connection_info.to_runtime_file().unwrap();
+    assert_eq!(file_path.parent().unwrap(), runtime_dir.as_path());
+
+    let mode = std::fs::metadata(&file_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // the file we just wrote shows up as a running Kernel, other files in the dir are ignored
+    std::fs::write(runtime_dir.join("not-a-kernel.json"), "{}").unwrap();
+    let running = ConnectionInfo::discover_running().unwrap();
+    let found = running
+        .iter()
+        .find(|(path, _)| path == &file_path)
+        .expect("Connection file not discovered");
+    assert_eq!(found.1.key, connection_info.key);
+    assert_eq!(found.1.kernel_name(), Some("secure"));
+    assert!(running
+        .iter()
+        .all(|(path, _)| !path.ends_with("not-a-kernel.json")));
+    std::fs::remove_file(file_path).unwrap();
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use kernel_sidecar::client::Client;
+use kernel_sidecar::execution_queue::{CancelToken, ExecutionResult, QueueMode};
+use kernel_sidecar::handlers::{
+    ErrorPolicy, FallibleHandler, HandlerContext, HandlerError, HandlerSet,
+};
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::jupyter::response::Response;
+use kernel_sidecar::jupyter::shell_content::execute::{ExecuteReply, ExecuteStatus};
+
+mod fake_kernel;
+use fake_kernel::FakeKernel;
+
+#[test]
+fn test_execute_reply_results() {
+    let ok = serde_json::json!({"status": "ok", "execution_count": 1, "user_expressions": {}});
+    let reply: ExecuteReply = Bytes::from(ok.to_string()).into();
+    assert_eq!(
+        ExecutionResult::from(reply),
+        ExecutionResult::Ok {
+            execution_count: Some(1)
+        }
+    );
+
+    let error = serde_json::json!({
+        "status": "error",
+        "execution_count": 2,
+        "ename": "ZeroDivisionError",
+        "evalue": "division by zero",
+        "traceback": ["Traceback..."],
+    });
+    let reply: ExecuteReply = Bytes::from(error.to_string()).into();
+    match ExecutionResult::from(reply) {
+        ExecutionResult::Error { ename, .. } => assert_eq!(ename, "ZeroDivisionError"),
+        other => panic!("Expected Error, got {:?}", other),
+    }
+
+    // Aborted replies can leave out execution_count
+    let aborted = serde_json::json!({"status": "aborted"});
+    let reply: ExecuteReply = Bytes::from(aborted.to_string()).into();
+    assert_eq!(ExecutionResult::from(reply), ExecutionResult::Aborted);
+
+    // Older Kernels say abort
+    let abort = serde_json::json!({"status": "abort"});
+    let reply: ExecuteReply = Bytes::from(abort.to_string()).into();
+    assert_eq!(ExecutionResult::from(reply), ExecutionResult::Aborted);
+
+    // Statuses nobody has heard of don't count as success
+    let unknown = serde_json::json!({"status": "starting", "execution_count": 3});
+    let reply: ExecuteReply = Bytes::from(unknown.to_string()).into();
+    assert_eq!(reply.status, ExecuteStatus::Unknown("starting".to_string()));
+    assert!(matches!(
+        ExecutionResult::from(reply),
+        ExecutionResult::Error {
+            execution_count: Some(3),
+            ..
+        }
+    ));
+}
+
+// The FakeKernel ignores stop_on_error, cells after a failing one must not be sent at all
+#[tokio::test]
+async fn test_halt_on_error_without_stop_on_error() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let fake = FakeKernel::start(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let results = client
+        .execution_queue(QueueMode::HaltOnError)
+        .push("x = 1", vec![])
+        .push("raise", vec![])
+        .push("x + 1", vec![])
+        .run()
+        .await;
+    assert!(matches!(results[0], ExecutionResult::Ok { .. }));
+    assert!(matches!(results[1], ExecutionResult::Error { .. }));
+    assert_eq!(results[2], ExecutionResult::Aborted);
+    let sent: Vec<String> = fake
+        .received("execute_request")
+        .iter()
+        .map(|received| received.content["code"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(sent, vec!["x = 1", "raise"]);
+
+    // Continue sends everything
+    let results = client
+        .execution_queue(QueueMode::Continue)
+        .push("raise", vec![])
+        .push("reply_status abort", vec![])
+        .push("x + 1", vec![])
+        .run()
+        .await;
+    assert!(matches!(results[0], ExecutionResult::Error { .. }));
+    assert_eq!(results[1], ExecutionResult::Aborted);
+    assert!(matches!(results[2], ExecutionResult::Ok { .. }));
+}
+
+// Cancelled cells that weren't sent yet never reach the Kernel, and don't halt the queue
+#[tokio::test]
+async fn test_cancel_queued_cells() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let fake = FakeKernel::start(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let running = CancelToken::new();
+    let waiting = CancelToken::new();
+    let queue = client
+        .execution_queue(QueueMode::HaltOnError)
+        .push_cancellable("sleep 10", vec![], &running)
+        .push_cancellable("y = 2", vec![], &waiting)
+        .push("x + 1", vec![]);
+    let run = tokio::spawn(queue.run());
+    fake.wait_for("execute_request", 1).await;
+    waiting.cancel();
+    running.cancel();
+
+    let results = tokio::time::timeout(Duration::from_secs(5), run)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(results[0], ExecutionResult::Cancelled);
+    assert_eq!(results[1], ExecutionResult::Cancelled);
+    assert!(matches!(results[2], ExecutionResult::Ok { .. }));
+    assert_eq!(fake.received("interrupt_request").len(), 1);
+    let sent: Vec<String> = fake
+        .received("execute_request")
+        .iter()
+        .map(|received| received.content["code"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(sent, vec!["sleep 10", "x + 1"]);
+}
+
+// A handler aborting its Action means the reply is never captured, that is not a dead Kernel
+#[tokio::test]
+async fn test_handler_abort_is_not_kernel_died() {
+    #[derive(Debug)]
+    struct NoInputs;
+
+    #[async_trait::async_trait]
+    impl FallibleHandler for NoInputs {
+        async fn try_handle(
+            &mut self,
+            msg: &Response,
+            _ctx: &HandlerContext,
+        ) -> Result<(), HandlerError> {
+            match msg {
+                Response::ExecuteInput(_) => Err("no inputs allowed".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _fake = FakeKernel::start(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let results = client
+        .execution_queue(QueueMode::HaltOnError)
+        .push(
+            "x = 1",
+            HandlerSet::new().with_fallible(NoInputs, ErrorPolicy::Abort),
+        )
+        .push("x + 1", vec![])
+        .run()
+        .await;
+    assert_eq!(
+        results[0],
+        ExecutionResult::HandlerAborted {
+            errors: vec!["execute_input: no inputs allowed".to_string()]
+        }
+    );
+    assert_eq!(results[1], ExecutionResult::Aborted);
+}
+
+#[cfg(feature = "test_ipython")]
+mod test_utils;
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_halt_on_error() {
+    let (_kernel, client) = test_utils::start_kernel().await;
+    let results = client
+        .execution_queue(QueueMode::HaltOnError)
+        .push("x = 1", vec![])
+        .push("1 / 0", vec![])
+        .push("x + 1", vec![])
+        .run()
+        .await;
+    assert!(matches!(results[0], ExecutionResult::Ok { .. }));
+    assert!(matches!(results[1], ExecutionResult::Error { .. }));
+    assert_eq!(results[2], ExecutionResult::Aborted);
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_continue_on_error() {
+    let (_kernel, client) = test_utils::start_kernel().await;
+    let results = client
+        .execution_queue(QueueMode::Continue)
+        .push("x = 1", vec![])
+        .push("1 / 0", vec![])
+        .push("x + 1", vec![])
+        .run()
+        .await;
+    assert!(matches!(results[0], ExecutionResult::Ok { .. }));
+    assert!(matches!(results[1], ExecutionResult::Error { .. }));
+    assert!(matches!(results[2], ExecutionResult::Ok { .. }));
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use kernel_sidecar::executor::{
+    CellReport, CellStatus, ExecutionReport, ExecutorError, NotebookExecutor,
+};
+use kernel_sidecar::notebook::Notebook;
+
+fn cell(status: CellStatus) -> CellReport {
+    CellReport {
+        cell_id: uuid::Uuid::new_v4().to_string(),
+        status,
+        execution_count: None,
+        duration: Duration::ZERO,
+    }
+}
+
+fn error() -> CellStatus {
+    CellStatus::Error {
+        ename: "ZeroDivisionError".to_string(),
+        evalue: "division by zero".to_string(),
+    }
+}
+
+#[test]
+fn test_report_success() {
+    let mut report = ExecutionReport {
+        kernel_name: "python3".to_string(),
+        cells: vec![cell(CellStatus::Ok), cell(error()), cell(CellStatus::Ok)],
+        duration: Duration::ZERO,
+        allow_errors: true,
+    };
+    assert!(report.is_success());
+    assert_eq!(report.failed_cell(), None);
+
+    report.allow_errors = false;
+    assert!(!report.is_success());
+    assert_eq!(report.failed_cell(), Some(&report.cells[1]));
+
+    let report = ExecutionReport {
+        kernel_name: "python3".to_string(),
+        cells: vec![cell(CellStatus::TimedOut), cell(CellStatus::Skipped)],
+        duration: Duration::ZERO,
+        allow_errors: true,
+    };
+    assert!(!report.is_success());
+    assert_eq!(report.failed_cell(), Some(&report.cells[0]));
+}
+
+#[tokio::test]
+async fn test_kernel_selection_errors() {
+    // No metadata.kernelspec and no kernel_name
+    let nb = Notebook::new();
+    match NotebookExecutor::new().execute(nb).await {
+        Err(ExecutorError::NoKernelSpec) => {}
+        other => panic!("Expected NoKernelSpec, got {:?}", other),
+    }
+
+    let mut nb = Notebook::new();
+    nb.metadata = serde_json::json!({"kernelspec": {"name": "does-not-exist"}});
+    match NotebookExecutor::new().execute(nb).await {
+        Err(ExecutorError::SpecNotFound(name)) => assert_eq!(name, "does-not-exist"),
+        other => panic!("Expected SpecNotFound, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_execute_file_errors() {
+    let input = std::env::temp_dir().join(format!("{}.ipynb", uuid::Uuid::new_v4()));
+    let output = std::env::temp_dir().join(format!("{}.ipynb", uuid::Uuid::new_v4()));
+    match NotebookExecutor::new().execute_file(&input, &output).await {
+        Err(ExecutorError::Io(error)) => assert_eq!(error.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("Expected Io, got {:?}", other),
+    }
+
+    std::fs::write(&input, "not a notebook").unwrap();
+    match NotebookExecutor::new().execute_file(&input, &output).await {
+        Err(ExecutorError::Parse(_)) => {}
+        other => panic!("Expected Parse, got {:?}", other),
+    }
+    assert!(!output.exists());
+    std::fs::remove_file(input).unwrap();
+}
+
+#[cfg(feature = "test_ipython")]
+fn python_notebook(sources: &[&str]) -> Notebook {
+    let mut nb = Notebook::new();
+    nb.metadata = serde_json::json!({"kernelspec": {"name": "python3"}});
+    for source in sources {
+        nb.add_code_cell(source);
+    }
+    nb
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_execute_stops_at_error() {
+    use kernel_sidecar::notebook::Cell;
+
+    let mut nb = python_notebook(&["x = 1", "1 / 0", "x + 1", "y", "x + 3"]);
+    nb.add_markdown_cell("Markdown isn't run or reported");
+    // Tagged cells are allowed to fail
+    if let Cell::Code(cell) = &mut nb.cells[1] {
+        cell.metadata = serde_json::json!({"tags": ["raises-exception"]});
+    }
+
+    let (executed, report) = NotebookExecutor::new().execute(nb).await.unwrap();
+    let statuses: Vec<_> = report.cells.iter().map(|c| c.status.clone()).collect();
+    assert_eq!(statuses.len(), 5);
+    assert_eq!(statuses[0], CellStatus::Ok);
+    assert!(matches!(statuses[1], CellStatus::ExpectedError { .. }));
+    assert_eq!(statuses[2], CellStatus::Ok);
+    match &statuses[3] {
+        CellStatus::Error { ename, .. } => assert_eq!(ename, "NameError"),
+        other => panic!("Expected NameError, got {:?}", other),
+    }
+    assert_eq!(statuses[4], CellStatus::Skipped);
+    assert!(!report.is_success());
+    assert_eq!(
+        report.failed_cell().unwrap().cell_id,
+        report.cells[3].cell_id
+    );
+    assert_eq!(report.cells[2].execution_count, Some(3));
+
+    // Outputs made it into the executed notebook
+    match executed.get_cell(&report.cells[2].cell_id).unwrap() {
+        Cell::Code(cell) => {
+            let result = cell.outputs[0].as_execute_result().unwrap();
+            assert_eq!(result.data["text/plain"], "2");
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_execute_allow_errors_and_timeout() {
+    let nb = python_notebook(&["1 / 0", "import time; time.sleep(30)", "2 + 2"]);
+    let (executed, report) = NotebookExecutor::new()
+        .allow_errors(true)
+        .cell_timeout(Duration::from_secs(2))
+        .execute(nb)
+        .await
+        .unwrap();
+    assert!(matches!(report.cells[0].status, CellStatus::Error { .. }));
+    assert_eq!(report.cells[1].status, CellStatus::TimedOut);
+    // The interrupt's traceback made it into the executed notebook
+    match executed.get_cell(&report.cells[1].cell_id).unwrap() {
+        kernel_sidecar::notebook::Cell::Code(cell) => {
+            let error = cell.outputs.last().unwrap().as_error().unwrap();
+            assert_eq!(error.ename, "KeyboardInterrupt");
+        }
+        _ => unreachable!(),
+    }
+    assert!(report.cells[1].duration >= Duration::from_secs(2));
+    assert_eq!(report.cells[2].status, CellStatus::Skipped);
+    assert!(!report.is_success());
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_execute_file() {
+    let input = std::env::temp_dir().join(format!("{}.ipynb", uuid::Uuid::new_v4()));
+    let output = std::env::temp_dir().join(format!("{}.ipynb", uuid::Uuid::new_v4()));
+    python_notebook(&["import os; os.getcwd()"]).save(input.to_str().unwrap());
+
+    let report = NotebookExecutor::new()
+        .execute_file(&input, &output)
+        .await
+        .unwrap();
+    assert!(report.is_success());
+
+    // Ran in the notebook's directory
+    let executed = Notebook::from_file(output.to_str().unwrap());
+    match &executed.cells[0] {
+        kernel_sidecar::notebook::Cell::Code(cell) => {
+            let cwd = cell.outputs[0].as_execute_result().unwrap().data["text/plain"].clone();
+            let expected = std::env::temp_dir().canonicalize().unwrap();
+            assert_eq!(cwd, format!("'{}'", expected.to_str().unwrap()));
+        }
+        _ => unreachable!(),
+    }
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
@@ -0,0 +1,418 @@
+use std::sync::Arc;
+
+use kernel_sidecar::client::Client;
+use kernel_sidecar::handlers::{
+    handler_fn, on_output, Handler, HandlerContext, HandlerExt, HandlerSet, MessageCountHandler,
+    SimpleOutputHandler,
+};
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::jupyter::header::Header;
+use kernel_sidecar::jupyter::iopub_content::status::{KernelStatus, Status};
+use kernel_sidecar::jupyter::iopub_content::stream::{Stream, StreamName};
+use kernel_sidecar::jupyter::message::Message;
+use kernel_sidecar::jupyter::response::Response;
+use kernel_sidecar::jupyter::shell_content::execute::ExecuteRequest;
+use kernel_sidecar::notebook::Output;
+use tokio::sync::Mutex;
+
+mod fake_kernel;
+use fake_kernel::{bind_channels, FakeChannels, FakeKernel};
+
+fn message<T>(msg_type: &str, content: T) -> Message<T> {
+    Message {
+        header: Header::new(msg_type.to_string()),
+        parent_header: None,
+        metadata: None,
+        content,
+    }
+}
+
+fn stream(text: &str) -> Response {
+    Response::Stream(message(
+        "stream",
+        Stream {
+            name: StreamName::Stdout,
+            text: text.to_string(),
+        },
+    ))
+}
+
+fn status(execution_state: KernelStatus) -> Response {
+    Response::Status(message("status", Status { execution_state }))
+}
+
+// Context for calling handle directly, as if for an execute_request sent on a Client
+async fn context() -> (FakeChannels, HandlerContext) {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let channels = bind_channels(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    let request = ExecuteRequest::new("2 + 2".to_string()).into();
+    (channels, HandlerContext::new(request, &client))
+}
+
+#[tokio::test]
+async fn test_handler_fn() {
+    let (_channels, ctx) = context().await;
+    let seen = Arc::new(std::sync::Mutex::new(vec![]));
+    let seen_clone = seen.clone();
+    let mut handler = handler_fn(move |msg: Response, ctx: HandlerContext| {
+        let seen = seen_clone.clone();
+        async move {
+            seen.lock().unwrap().push((msg.msg_type(), ctx.msg_id()));
+        }
+    });
+    handler.handle(&status(KernelStatus::Busy), &ctx).await;
+    handler.handle(&stream("foo"), &ctx).await;
+    let msg_id = ctx.msg_id();
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            ("status".to_string(), msg_id.clone()),
+            ("stream".to_string(), msg_id)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_on_output() {
+    let (_channels, ctx) = context().await;
+    let outputs = Arc::new(std::sync::Mutex::new(vec![]));
+    let outputs_clone = outputs.clone();
+    let mut handler = on_output(move |output: Output, _ctx: HandlerContext| {
+        let outputs = outputs_clone.clone();
+        async move {
+            outputs.lock().unwrap().push(output);
+        }
+    });
+    handler.handle(&status(KernelStatus::Busy), &ctx).await;
+    handler.handle(&stream("foo"), &ctx).await;
+    handler.handle(&status(KernelStatus::Idle), &ctx).await;
+    let outputs = outputs.lock().unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].as_stream().unwrap().text, "foo");
+}
+
+#[tokio::test]
+async fn test_combinators() {
+    let (_channels, ctx) = context().await;
+    // Only streams make it to the counter, and they're upper-cased on the way to the outputs
+    let mut handler = MessageCountHandler::new().filter_msg_type(&["stream"]).tee(
+        SimpleOutputHandler::new().map(|msg| match msg {
+            Response::Stream(m) => stream(&m.content.text.to_uppercase()),
+            other => other.clone(),
+        }),
+    );
+    handler.handle(&status(KernelStatus::Busy), &ctx).await;
+    handler.handle(&stream("foo"), &ctx).await;
+    handler.handle(&status(KernelStatus::Idle), &ctx).await;
+
+    let (counter, outputs) = handler.into_inner();
+    let counter = counter.into_inner();
+    assert_eq!(counter.counts.get("status"), None);
+    assert_eq!(counter.counts["stream"], 1);
+    let outputs = outputs.into_inner();
+    assert_eq!(outputs.output[0].as_stream().unwrap().text, "FOO");
+}
+
+#[test]
+fn test_handler_set() {
+    let counter = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers = HandlerSet::new()
+        .shared(counter.clone())
+        .with(SimpleOutputHandler::new());
+    assert_eq!(handlers.len(), 2);
+
+    // Existing Vec<Arc<Mutex<dyn Handler>>> code keeps working
+    let handlers: Vec<Arc<Mutex<dyn Handler>>> = vec![counter];
+    let handlers: HandlerSet = handlers.into();
+    assert_eq!(handlers.len(), 1);
+}
+
+#[tokio::test]
+async fn test_output_handler_deleted_cell() {
+    use kernel_sidecar::handlers::outputs::OutputHandler;
+    use kernel_sidecar::handlers::FallibleHandler;
+    use kernel_sidecar::notebook::Notebook;
+
+    let (_channels, ctx) = context().await;
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+    let cell = nb.lock().await.add_code_cell("print('foo')");
+    let mut handler = OutputHandler::new(nb.clone(), cell.id());
+    handler.try_handle(&stream("foo"), &ctx).await.unwrap();
+
+    // Deleted mid-run
+    nb.lock().await.cells.clear();
+    // Status messages don't touch the cell, so they're still fine
+    handler
+        .try_handle(&status(KernelStatus::Idle), &ctx)
+        .await
+        .unwrap();
+    let err = handler.try_handle(&stream("bar"), &ctx).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Cell {} not found in Notebook", cell.id())
+    );
+}
+
+#[tokio::test]
+async fn test_output_handler_execution_count_and_timing() {
+    use kernel_sidecar::handlers::outputs::OutputHandler;
+    use kernel_sidecar::notebook::{Cell, Notebook};
+    use serde_json::json;
+
+    let (_channels, ctx) = context().await;
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+    let cell = nb.lock().await.add_code_cell("2 + 2");
+    let mut handler = OutputHandler::new(nb.clone(), cell.id()).with_timing();
+
+    let execute_input = Response::ExecuteInput(message(
+        "execute_input",
+        serde_json::from_value(json!({"code": "2 + 2", "execution_count": 3})).unwrap(),
+    ));
+    let mut execute_reply = message(
+        "execute_reply",
+        serde_json::from_value(json!({"status": "ok", "execution_count": 3})).unwrap(),
+    );
+    execute_reply.metadata =
+        Some(serde_json::from_value(json!({"started": "2024-01-01T12:00:00.5Z"})).unwrap());
+
+    handler.handle(&status(KernelStatus::Busy), &ctx).await;
+    handler.handle(&execute_input, &ctx).await;
+    handler
+        .handle(&Response::Execute(execute_reply), &ctx)
+        .await;
+    handler.handle(&status(KernelStatus::Idle), &ctx).await;
+
+    let nb = nb.lock().await;
+    let Cell::Code(cell) = nb.get_cell(cell.id()).unwrap() else {
+        unreachable!()
+    };
+    assert_eq!(cell.execution_count, Some(3));
+    let execution = cell.metadata["execution"].as_object().unwrap();
+    let mut keys: Vec<_> = execution.keys().cloned().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "iopub.execute_input",
+            "iopub.status.busy",
+            "iopub.status.idle",
+            "shell.execute_reply",
+            "shell.execute_reply.started"
+        ]
+    );
+    assert_eq!(
+        execution["shell.execute_reply.started"],
+        "2024-01-01T12:00:00.500000Z"
+    );
+}
+
+#[cfg(feature = "test_ipython")]
+mod test_utils;
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_fallible_handler_policies() {
+    use kernel_sidecar::actions::ActionStatus;
+    use kernel_sidecar::handlers::{ErrorPolicy, FallibleHandler, HandlerError};
+
+    // Fails on every stream message
+    #[derive(Debug)]
+    struct NoStreams;
+
+    #[async_trait::async_trait]
+    impl FallibleHandler for NoStreams {
+        async fn try_handle(
+            &mut self,
+            msg: &Response,
+            _ctx: &HandlerContext,
+        ) -> Result<(), HandlerError> {
+            match msg {
+                Response::Stream(_) => Err("no streams allowed".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let (_kernel, client) = test_utils::start_kernel().await;
+
+    let handlers = HandlerSet::new().with_fallible(NoStreams, ErrorPolicy::Continue);
+    let outcome = client
+        .execute_request("print('foo')".to_string(), handlers)
+        .await
+        .await;
+    assert_eq!(outcome, ActionStatus::Completed);
+    assert_eq!(outcome.errors.len(), 1);
+    assert_eq!(outcome.errors[0].msg_type, "stream");
+
+    let counter = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers = HandlerSet::new()
+        .with_fallible(NoStreams, ErrorPolicy::Abort)
+        .shared(counter.clone());
+    let outcome = client
+        .execute_request("print('foo')".to_string(), handlers)
+        .await
+        .await;
+    assert_eq!(outcome, ActionStatus::Aborted);
+    assert_eq!(outcome.errors[0].error.to_string(), "no streams allowed");
+    // Handlers after the failing one never saw the stream
+    assert!(!counter.lock().await.counts.contains_key("stream"));
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_follow_up_request_from_context() {
+    use kernel_sidecar::jupyter::request::Request;
+
+    let (_kernel, client) = test_utils::start_kernel().await;
+
+    // Once the first cell's reply comes in, run a second cell on the same Client
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let handler = handler_fn(move |msg: Response, ctx: HandlerContext| {
+        let tx = tx.clone();
+        async move {
+            if let Response::Execute(_) = msg {
+                assert!(matches!(ctx.request(), Request::Execute(_)));
+                let follow_up = ctx
+                    .client()
+                    .unwrap()
+                    .execute_request("y = x + 1".to_string(), vec![])
+                    .await;
+                tx.send((ctx.msg_id(), follow_up)).unwrap();
+            }
+        }
+    });
+    let action = client
+        .execute_request("x = 1".to_string(), HandlerSet::new().with(handler))
+        .await;
+    let msg_id = action.request.msg_id();
+    action.await;
+
+    let (ctx_msg_id, follow_up) = rx.recv().await.unwrap();
+    assert_eq!(ctx_msg_id, msg_id);
+    assert_eq!(
+        follow_up.await,
+        kernel_sidecar::actions::ActionStatus::Completed
+    );
+
+    let outputs = Arc::new(Mutex::new(SimpleOutputHandler::new()));
+    client
+        .execute_request("y".to_string(), HandlerSet::new().shared(outputs.clone()))
+        .await
+        .await;
+    assert_eq!(
+        outputs.lock().await.output[0]
+            .as_execute_result()
+            .unwrap()
+            .data["text/plain"],
+        "2"
+    );
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_concurrent_dispatch() {
+    use kernel_sidecar::actions::ActionStatus;
+    use kernel_sidecar::handlers::DispatchMode;
+    use kernel_sidecar::jupyter::iopub_content::status::KernelStatus;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    let (_kernel, client) = test_utils::start_kernel().await;
+
+    // The slow handler is stuck on the first message until the fast one has seen the Kernel go
+    // idle, which would deadlock if they ran one after another
+    let released = Arc::new(Notify::new());
+    let slow_released = released.clone();
+    let slow_seen = Arc::new(std::sync::Mutex::new(vec![]));
+    let slow_seen_clone = slow_seen.clone();
+    let slow = handler_fn(move |msg: Response, _ctx: HandlerContext| {
+        let released = slow_released.clone();
+        let seen = slow_seen_clone.clone();
+        async move {
+            if seen.lock().unwrap().is_empty() {
+                released.notified().await;
+            }
+            seen.lock().unwrap().push(msg.msg_type());
+        }
+    });
+    let fast_seen = Arc::new(std::sync::Mutex::new(vec![]));
+    let fast_seen_clone = fast_seen.clone();
+    let fast = handler_fn(move |msg: Response, _ctx: HandlerContext| {
+        let released = released.clone();
+        let seen = fast_seen_clone.clone();
+        async move {
+            if let Response::Status(status) = &msg {
+                if status.content.execution_state == KernelStatus::Idle {
+                    released.notify_one();
+                }
+            }
+            seen.lock().unwrap().push(msg.msg_type());
+        }
+    });
+
+    let handlers = HandlerSet::new()
+        .with(slow)
+        .with(fast)
+        .dispatch_mode(DispatchMode::Concurrent { queue_size: 16 });
+    let action = client
+        .execute_request("print('foo')".to_string(), handlers)
+        .await;
+    let outcome = tokio::time::timeout(Duration::from_secs(10), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Completed);
+
+    // Both got every message, in the same order, by the time the Action completed
+    let slow_seen = slow_seen.lock().unwrap().clone();
+    assert_eq!(slow_seen, *fast_seen.lock().unwrap());
+    assert_eq!(slow_seen.first().unwrap(), "status");
+    assert!(slow_seen.contains(&"stream".to_string()));
+}
+
+// The failing message is still queued when the Kernel goes idle, finishing the Action has to pick
+// up the abort
+#[tokio::test]
+async fn test_concurrent_abort_while_finishing() {
+    use std::time::Duration;
+
+    use kernel_sidecar::actions::ActionStatus;
+    use kernel_sidecar::handlers::{DispatchMode, ErrorPolicy, FallibleHandler, HandlerError};
+
+    #[derive(Debug)]
+    struct SlowNoReplies;
+
+    #[async_trait::async_trait]
+    impl FallibleHandler for SlowNoReplies {
+        async fn try_handle(
+            &mut self,
+            msg: &Response,
+            _ctx: &HandlerContext,
+        ) -> Result<(), HandlerError> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            match msg {
+                Response::Execute(_) => Err("no replies allowed".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _fake = FakeKernel::start(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let handlers = HandlerSet::new()
+        .with_fallible(SlowNoReplies, ErrorPolicy::Abort)
+        .dispatch_mode(DispatchMode::Concurrent { queue_size: 16 });
+    let action = client.execute_request("x = 1".to_string(), handlers).await;
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Aborted);
+    assert_eq!(outcome.errors.len(), 1);
+    assert_eq!(outcome.errors[0].msg_type, "execute_reply");
+}
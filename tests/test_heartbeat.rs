@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use kernel_sidecar::actions::ActionStatus;
+use kernel_sidecar::client::Client;
+use kernel_sidecar::heartbeat::{HeartbeatConfig, Liveness};
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+
+mod fake_kernel;
+use fake_kernel::{bind_channels, serve_heartbeat};
+
+#[tokio::test]
+async fn test_wait_for_ready_timeout() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    let err = client
+        .wait_for_ready(Duration::from_millis(300))
+        .await
+        .unwrap_err();
+    assert_eq!(err.0, Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn test_heartbeat_monitor() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let server = serve_heartbeat(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    client.wait_for_ready(Duration::from_secs(5)).await.unwrap();
+
+    let config = HeartbeatConfig::default()
+        .interval(Duration::from_millis(50))
+        .timeout(Duration::from_millis(100))
+        .max_missed(3);
+    client.start_heartbeat_monitor(config);
+    let mut liveness = client.liveness();
+    assert_eq!(*liveness.borrow(), Liveness::Alive);
+
+    // Nothing answers this request, only the heartbeat monitor can finish it
+    let action = client.execute_request("2 + 2".to_string(), vec![]).await;
+
+    server.abort();
+    liveness.changed().await.unwrap();
+    assert_eq!(*liveness.borrow(), Liveness::Unresponsive);
+    liveness.changed().await.unwrap();
+    assert_eq!(*liveness.borrow(), Liveness::Dead);
+
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::KernelDied);
+}
+
+// The heartbeat answers but nothing else does, wait_until_ready gives up instead of hanging
+#[tokio::test]
+async fn test_wait_until_ready_gives_up() {
+    use kernel_sidecar::client::ReadyError;
+
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let server = serve_heartbeat(&connection_info).await;
+    let client = Client::new(connection_info).await;
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.wait_until_ready(Duration::from_millis(500)),
+    )
+    .await
+    .unwrap()
+    .unwrap_err();
+    assert_eq!(err, ReadyError::Timeout(Duration::from_millis(500)));
+
+    // Or once the heartbeat monitor declares the Kernel Dead, well before the timeout
+    let config = HeartbeatConfig::default()
+        .interval(Duration::from_millis(50))
+        .timeout(Duration::from_millis(100))
+        .max_missed(3);
+    client.start_heartbeat_monitor(config);
+    let waiting = client.wait_until_ready(Duration::from_secs(60));
+    server.abort();
+    let err = tokio::time::timeout(Duration::from_secs(5), waiting)
+        .await
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err, ReadyError::KernelDied);
+}
@@ -0,0 +1,53 @@
+use bytes::Bytes;
+use kernel_sidecar::jupyter::shell_content::kernel_info::KernelInfoReply;
+
+#[test]
+fn test_kernel_info_reply() {
+    let content = serde_json::json!({
+        "status": "ok",
+        "protocol_version": "5.5",
+        "implementation": "ipython",
+        "implementation_version": "8.17.2",
+        "language_info": {
+            "name": "python",
+            "version": "3.11.6",
+            "mimetype": "text/x-python",
+            "codemirror_mode": {"name": "ipython", "version": 3},
+            "pygments_lexer": "ipython3",
+            "nbconvert_exporter": "python",
+            "file_extension": ".py"
+        },
+        "banner": "Python 3.11.6",
+        "help_links": [{"text": "Python Reference", "url": "https://docs.python.org/3.11"}],
+        "debugger": true,
+        "supported_features": ["debugger"]
+    });
+    let reply: KernelInfoReply = Bytes::from(content.to_string()).into();
+    assert_eq!(reply.language_info.name, "python");
+    assert_eq!(reply.protocol_version(), Some((5, 5)));
+    assert_eq!(reply.help_links.unwrap()[0].text, "Python Reference");
+    assert!(reply.debugger);
+    assert_eq!(reply.supported_features, vec!["debugger"]);
+}
+
+#[test]
+fn test_kernel_info_reply_before_5_3() {
+    // Older Kernels don't send debugger or supported_features
+    let content = serde_json::json!({
+        "status": "ok",
+        "protocol_version": "5",
+        "implementation": "irkernel",
+        "implementation_version": "1.3.2",
+        "language_info": {
+            "name": "R",
+            "version": "4.3.2",
+            "mimetype": "text/x-r-source",
+            "file_extension": ".r"
+        },
+        "banner": "R version 4.3.2"
+    });
+    let reply: KernelInfoReply = Bytes::from(content.to_string()).into();
+    assert_eq!(reply.protocol_version(), Some((5, 0)));
+    assert!(!reply.debugger);
+    assert!(reply.supported_features.is_empty());
+}
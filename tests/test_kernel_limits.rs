@@ -0,0 +1,96 @@
+#![cfg(target_os = "linux")]
+use std::time::Duration;
+
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::kernels::{
+    JupyterKernel, KernelEvent, KernelLaunchOptions, ProcessGroup, ResourceLimit, ResourceLimits,
+};
+
+#[test]
+fn test_limits_applied() {
+    // Fake Kernel that reports the limits it was started with
+    let connection_info = ConnectionInfo::new(Some("limits".to_string())).unwrap();
+    let argv = vec!["sh", "-c", "ulimit -n; ulimit -t"];
+    let limits = ResourceLimits::new().open_files(64).cpu_seconds(100);
+    let options = KernelLaunchOptions::new()
+        .silent(true)
+        .limits(limits)
+        .process_group(ProcessGroup::NewSession);
+    let kernel = JupyterKernel::start(argv, connection_info, options);
+
+    let mut lines = vec![];
+    for _ in 0..50 {
+        lines = kernel.logs().into_iter().map(|l| l.line).collect();
+        if lines.len() == 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(lines, vec!["64", "100"]);
+}
+
+#[tokio::test]
+async fn test_cpu_limit_event() {
+    let connection_info = ConnectionInfo::new(Some("spin".to_string())).unwrap();
+    let argv = vec!["sh", "-c", "while :; do :; done"];
+    let options = KernelLaunchOptions::new()
+        .silent(true)
+        .limits(ResourceLimits::new().cpu_seconds(1));
+    let kernel = JupyterKernel::start(argv, connection_info, options);
+    let mut events = kernel.events();
+
+    let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+        .await
+        .expect("Timed out waiting for Kernel to hit its CPU limit")
+        .unwrap();
+    match event {
+        KernelEvent::ResourceLimitExceeded { limit, .. } => {
+            assert_eq!(limit, ResourceLimit::CpuTime)
+        }
+        other => panic!("Expected ResourceLimitExceeded, got {:?}", other),
+    }
+    let died = kernel.check_alive().unwrap_err();
+    assert_eq!(died.limit_exceeded, Some(ResourceLimit::CpuTime));
+}
+
+#[tokio::test]
+async fn test_external_sigkill_is_not_a_cpu_limit() {
+    // A SIGKILL from someone else doesn't mean the Kernel ran out of CPU time
+    let connection_info = ConnectionInfo::new(Some("sigkill".to_string())).unwrap();
+    let argv = vec!["sh", "-c", "sleep 60"];
+    let options = KernelLaunchOptions::new()
+        .silent(true)
+        .limits(ResourceLimits::new().cpu_seconds(100));
+    let kernel = JupyterKernel::start(argv, connection_info, options);
+    let mut events = kernel.events();
+
+    unsafe { libc::kill(kernel.pid() as libc::pid_t, libc::SIGKILL) };
+    let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+        .await
+        .expect("Timed out waiting for Kernel to exit")
+        .unwrap();
+    assert!(matches!(event, KernelEvent::Exited(_)), "{:?}", event);
+    let died = kernel.check_alive().unwrap_err();
+    assert_eq!(died.limit_exceeded, None);
+}
+
+#[tokio::test]
+async fn test_log_messages_are_only_a_likely_cause() {
+    // Printing a MemoryError doesn't prove the address_space limit was hit
+    let connection_info = ConnectionInfo::new(Some("oom".to_string())).unwrap();
+    let argv = vec!["sh", "-c", "sleep 0.2; echo MemoryError >&2; exit 1"];
+    let options = KernelLaunchOptions::new()
+        .silent(true)
+        .limits(ResourceLimits::new().address_space(1024 * 1024 * 1024));
+    let kernel = JupyterKernel::start(argv, connection_info, options);
+    let mut events = kernel.events();
+
+    let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+        .await
+        .expect("Timed out waiting for Kernel to exit")
+        .unwrap();
+    assert!(matches!(event, KernelEvent::Exited(_)), "{:?}", event);
+    let died = kernel.check_alive().unwrap_err();
+    assert_eq!(died.limit_exceeded, None);
+    assert_eq!(died.likely_cause, Some(ResourceLimit::AddressSpace));
+}
@@ -0,0 +1,58 @@
+#![cfg(unix)]
+use std::time::Duration;
+
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::kernels::{JupyterKernel, LogSource};
+
+#[test]
+fn test_logs_captured_when_kernel_dies() {
+    // Not a real Kernel, just something that writes to stdout/stderr and exits at startup
+    let connection_info = ConnectionInfo::new(Some("broken".to_string())).unwrap();
+    let argv = vec![
+        "sh",
+        "-c",
+        "echo starting {connection_file}; echo 'ModuleNotFoundError: ipykernel' >&2; exit 3",
+    ];
+    let kernel = JupyterKernel::start(argv, connection_info, true);
+
+    let mut died = None;
+    for _ in 0..50 {
+        if let Err(e) = kernel.check_alive() {
+            died = Some(e);
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let died = died.expect("Kernel process should have exited");
+    assert_eq!(died.status.code(), Some(3));
+
+    // Everything the Kernel wrote before dying is on the error, no waiting for the reader threads
+    let logs = died.logs;
+    assert_eq!(logs.len(), 2);
+    let stdout = logs.iter().find(|l| l.source == LogSource::Stdout).unwrap();
+    assert!(stdout.line.starts_with("starting "));
+    assert!(stdout.line.ends_with(".json"));
+    let stderr = logs.iter().find(|l| l.source == LogSource::Stderr).unwrap();
+    assert_eq!(stderr.line, "ModuleNotFoundError: ipykernel");
+}
+
+#[test]
+fn test_log_callback_can_add_callbacks() {
+    use kernel_sidecar::kernels::{KernelLogs, LogLine};
+    use std::sync::{Arc, Mutex};
+
+    let logs = KernelLogs::new(10);
+    let seen = Arc::new(Mutex::new(vec![]));
+    let (logs_clone, seen_clone) = (logs.clone(), seen.clone());
+    logs.add_callback(Arc::new(move |line: &LogLine| {
+        seen_clone.lock().unwrap().push(line.line.clone());
+        // Would deadlock if callbacks ran under the callbacks lock
+        logs_clone.add_callback(Arc::new(|_: &LogLine| {}));
+    }));
+    logs.push(LogLine {
+        source: LogSource::Stdout,
+        line: "hello".to_string(),
+        timestamp: chrono::Utc::now(),
+    });
+    assert_eq!(*seen.lock().unwrap(), vec!["hello"]);
+}
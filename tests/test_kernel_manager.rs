@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use kernel_sidecar::actions::ActionStatus;
+use kernel_sidecar::client::Client;
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::kernels::{JupyterKernel, KernelManager, KernelManagerError};
+
+mod fake_kernel;
+mod kernelspecs;
+use fake_kernel::FakeKernel;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_start_errors() {
+    kernelspecs::install();
+
+    let manager = KernelManager::new();
+    match manager.start("does-not-exist").await {
+        Err(KernelManagerError::SpecNotFound(name)) => assert_eq!(name, "does-not-exist"),
+        other => panic!("Expected SpecNotFound, got {:?}", other),
+    }
+    match manager.start("crashes").await {
+        Err(KernelManagerError::KernelDied(died)) => {
+            assert_eq!(died.status.code(), Some(1));
+        }
+        other => panic!("Expected KernelDied, got {:?}", other),
+    }
+    assert!(manager.list().is_empty());
+}
+
+// A do-nothing process from the message-interrupt kernelspec, with a FakeKernel on its ports
+#[cfg(unix)]
+async fn adopt_fake_kernel(manager: &KernelManager) -> (String, FakeKernel) {
+    kernelspecs::install();
+    let connection_info = ConnectionInfo::new(Some("message-interrupt".to_string())).unwrap();
+    let fake = FakeKernel::start(&connection_info).await;
+    let kernel = JupyterKernel::start(vec!["sleep", "60"], connection_info.clone(), true);
+    let client = Client::new(connection_info).await;
+    client
+        .wait_until_ready(Duration::from_secs(5))
+        .await
+        .unwrap();
+    (manager.adopt("message-interrupt", kernel, client), fake)
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_interrupt_by_message() {
+    let manager = KernelManager::new();
+    let (id, fake) = adopt_fake_kernel(&manager).await;
+
+    let client = manager.get(&id).unwrap();
+    let action = client.execute_request("sleep 10".to_string(), vec![]).await;
+    let msg_id = action.request.msg_id();
+    fake.wait_for("execute_request", 1).await;
+    manager.interrupt(&id).await.unwrap();
+
+    let interrupts = fake.wait_for("interrupt_request", 1).await;
+    assert_eq!(interrupts[0].executing, Some(msg_id));
+    let outcome = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .unwrap();
+    assert_eq!(outcome, ActionStatus::Completed);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_restart_keeps_kernel_managed() {
+    let manager = KernelManager::new();
+    let (id, _fake) = adopt_fake_kernel(&manager).await;
+    let pid = manager.list()[0].pid;
+
+    let (restarted, ()) = tokio::join!(manager.restart(&id), async {
+        for _ in 0..10 {
+            assert!(manager.get(&id).is_some());
+            assert_eq!(manager.list().len(), 1);
+            tokio::task::yield_now().await;
+        }
+    });
+    restarted.unwrap();
+    assert_ne!(manager.list()[0].pid, pid);
+
+    // Giving up on a restart part way leaves the Kernel managed
+    let _ = tokio::time::timeout(Duration::from_millis(1), manager.restart(&id)).await;
+    assert!(manager.get(&id).is_some());
+    manager.shutdown(&id).await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shutdown_asks_before_killing() {
+    let manager = KernelManager::new();
+    let (id, fake) = adopt_fake_kernel(&manager).await;
+    let pid = manager.list()[0].pid;
+
+    // The sleep process ignores the shutdown_request, so it's killed after the grace period
+    manager.shutdown(&id).await.unwrap();
+    assert_eq!(fake.received("shutdown_request").len(), 1);
+    assert!(manager.get(&id).is_none());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_ne!(unsafe { libc::kill(pid as libc::pid_t, 0) }, 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_cull_fake_kernel() {
+    use kernel_sidecar::kernels::CullingConfig;
+
+    let manager = KernelManager::new();
+    let (id, _fake) = adopt_fake_kernel(&manager).await;
+    let config = CullingConfig::new(Duration::from_millis(300))
+        .interval(Duration::from_millis(50))
+        .only_when_disconnected(true);
+    manager.enable_culling(config);
+
+    // Busy for longer than the idle timeout doesn't count as idle
+    let client = manager.get(&id).unwrap();
+    let action = client
+        .execute_request("sleep 0.8".to_string(), vec![])
+        .await;
+    drop(client);
+    assert_eq!(action.await, ActionStatus::Completed);
+    assert_eq!(manager.list().len(), 1);
+
+    // Idle, but someone is still holding a Client
+    let client = manager.get(&id).unwrap();
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(manager.list().len(), 1);
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(manager.list().is_empty());
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_manage_kernels() {
+    use std::sync::Arc;
+
+    use kernel_sidecar::handlers::{Handler, MessageCountHandler};
+    use tokio::sync::Mutex;
+
+    let manager = KernelManager::new();
+    let id1 = manager.start("python3").await.unwrap();
+    let id2 = manager.start("python3").await.unwrap();
+    assert_eq!(manager.list().len(), 2);
+
+    let pid = manager.list().iter().find(|k| k.id == id1).unwrap().pid;
+    manager.restart(&id1).await.unwrap();
+    let restarted = manager.list().into_iter().find(|k| k.id == id1).unwrap();
+    assert_ne!(restarted.pid, pid);
+
+    let client = manager.get(&id1).unwrap();
+    let handler = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers: Vec<Arc<Mutex<dyn Handler>>> = vec![handler.clone()];
+    client.kernel_info_request(handlers).await.await;
+    assert_eq!(handler.lock().await.counts["kernel_info_reply"], 1);
+
+    manager.shutdown(&id2).await.unwrap();
+    assert!(manager.get(&id2).is_none());
+    assert_eq!(manager.list().len(), 1);
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_cull_idle_kernels() {
+    use std::time::Duration;
+
+    use kernel_sidecar::kernels::CullingConfig;
+
+    let manager = KernelManager::new();
+    let id = manager.start("python3").await.unwrap();
+
+    // Holding onto a Client keeps the Kernel around when only culling disconnected Kernels
+    let client = manager.get(&id).unwrap();
+    let config = CullingConfig::new(Duration::from_millis(500))
+        .interval(Duration::from_millis(100))
+        .only_when_disconnected(true);
+    manager.enable_culling(config);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(manager.list().len(), 1);
+
+    drop(client);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(manager.list().is_empty());
+}
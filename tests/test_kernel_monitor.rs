@@ -0,0 +1,79 @@
+#![cfg(target_os = "linux")]
+use std::time::Duration;
+
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::kernels::{
+    JupyterKernel, KernelEvent, MonitorConfig, Threshold, ThresholdAction,
+};
+use tokio_stream::StreamExt;
+
+// Fake Kernel with a child process, so there's a process tree to sample
+fn start_fake_kernel() -> JupyterKernel {
+    let connection_info = ConnectionInfo::new(Some("monitor".to_string())).unwrap();
+    let argv = vec!["sh", "-c", "sleep 30 & wait"];
+    JupyterKernel::start(argv, connection_info, true)
+}
+
+#[tokio::test]
+async fn test_monitor_samples() {
+    let kernel = start_fake_kernel();
+    // give sh a moment to fork off sleep
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let monitor = kernel.monitor(MonitorConfig::new(Duration::from_millis(50)));
+    let mut samples = monitor.samples();
+
+    let sample = tokio::time::timeout(Duration::from_secs(5), samples.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sample.pids[0], kernel.pid());
+    assert_eq!(sample.pids.len(), 2);
+    assert!(sample.rss_bytes > 0);
+    assert!(sample.threads >= 2);
+    assert!(sample.open_fds > 0);
+    assert_eq!(monitor.latest().unwrap().pids, sample.pids);
+}
+
+#[tokio::test]
+async fn test_monitor_threshold_restart() {
+    let kernel = start_fake_kernel();
+    let original_pid = kernel.pid();
+    let mut events = kernel.events();
+    let config = MonitorConfig::new(Duration::from_millis(50))
+        .threshold(Threshold::Threads(0), ThresholdAction::Restart);
+    let _monitor = kernel.monitor(config);
+
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    match event {
+        KernelEvent::ThresholdExceeded {
+            threshold, action, ..
+        } => {
+            assert_eq!(threshold, Threshold::Threads(0));
+            assert_eq!(action, ThresholdAction::Restart);
+        }
+        other => panic!("Expected ThresholdExceeded, got {:?}", other),
+    }
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(event, KernelEvent::Restarted));
+    assert_ne!(kernel.pid(), original_pid);
+    assert!(kernel.check_alive().is_ok());
+}
+
+#[test]
+fn test_monitor_without_runtime() {
+    let kernel = start_fake_kernel();
+    let monitor = kernel.monitor(MonitorConfig::new(Duration::from_millis(50)));
+    for _ in 0..50 {
+        if monitor.latest().is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(monitor.latest().unwrap().pids[0], kernel.pid());
+}
@@ -0,0 +1,122 @@
+use kernel_sidecar::kernels::{KernelManagerError, KernelPool, PoolSpec};
+
+mod fake_kernel;
+mod kernelspecs;
+
+use fake_kernel::FakeKernel;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_pool_errors() {
+    kernelspecs::install();
+
+    let pool = KernelPool::new();
+    match pool.add(PoolSpec::new("does-not-exist", 1)) {
+        Err(KernelManagerError::SpecNotFound(name)) => assert_eq!(name, "does-not-exist"),
+        other => panic!("Expected SpecNotFound, got {:?}", other),
+    }
+    pool.add(PoolSpec::new("crashes", 2)).unwrap();
+    match pool.acquire("crashes").await {
+        Err(KernelManagerError::KernelDied(died)) => {
+            assert_eq!(died.status.code(), Some(1));
+        }
+        other => panic!("Expected KernelDied, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(pool.available("crashes"), 0);
+}
+
+// Acquiring while the refill task runs on another thread must not leave the pool short
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_pool_refills_to_size() {
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+
+    kernelspecs::install();
+    // Serve every hands-off Kernel that shows up with a FakeKernel
+    let server = tokio::spawn(async {
+        let mut served = HashSet::new();
+        let mut fakes = vec![];
+        loop {
+            for entry in std::fs::read_dir(kernelspecs::handed_off_dir()).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "json") && served.insert(path.clone())
+                {
+                    let connection_info = ConnectionInfo::from_file(&path).unwrap();
+                    fakes.push(FakeKernel::start(&connection_info).await);
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    let pool = KernelPool::new();
+    pool.add(PoolSpec::new("hands-off", 2)).unwrap();
+    let wait_for_size = || async {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while pool.available("hands-off") < 2 {
+            assert!(Instant::now() < deadline, "Pool never got back to size");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    wait_for_size().await;
+    let mut acquired = vec![];
+    for _ in 0..3 {
+        acquired.push(pool.acquire("hands-off").await.unwrap());
+        wait_for_size().await;
+    }
+    pool.remove("hands-off");
+    drop(acquired);
+    server.abort();
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_pool_acquire() {
+    use std::time::Duration;
+
+    use kernel_sidecar::handlers::{Handler, MessageCountHandler};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let pool = KernelPool::new();
+    pool.add(PoolSpec::new("python3", 2).warmup("x = 42"))
+        .unwrap();
+    while pool.available("python3") < 2 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Warm-up code already ran in the Kernel handed out
+    let (_kernel, client) = pool.acquire("python3").await.unwrap();
+    let handler = Arc::new(Mutex::new(MessageCountHandler::new()));
+    let handlers: Vec<Arc<Mutex<dyn Handler>>> = vec![handler.clone()];
+    client
+        .execute_request("x".to_string(), handlers)
+        .await
+        .await;
+    assert_eq!(handler.lock().await.counts["execute_result"], 1);
+
+    // And the pool refills in the background
+    while pool.available("python3") < 2 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_pool_warmup_error() {
+    use kernel_sidecar::execution_queue::ExecutionResult;
+
+    let pool = KernelPool::new();
+    pool.add(PoolSpec::new("python3", 1).warmup("1 / 0"))
+        .unwrap();
+    match pool.acquire("python3").await {
+        Err(KernelManagerError::WarmupFailed(ExecutionResult::Error { ename, .. })) => {
+            assert_eq!(ename, "ZeroDivisionError");
+        }
+        other => panic!("Expected WarmupFailed, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(pool.available("python3"), 0);
+}
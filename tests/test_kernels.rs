@@ -1,6 +1,9 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use kernel_sidecar::actions::ActionStatus;
 use kernel_sidecar::handlers::{Handler, MessageCountHandler};
+use kernel_sidecar::jupyter::iopub_content::status::KernelStatus;
 
 mod test_utils;
 use test_utils::start_kernel;
@@ -18,6 +21,10 @@ async fn test_kernel_info() {
     let counts = &handler.lock().await.counts;
     assert_eq!(counts["status"], 2);
     assert_eq!(counts["kernel_info_reply"], 1);
+
+    // Client keeps the latest reply around
+    let kernel_info = client.kernel_info().unwrap();
+    assert!(kernel_info.protocol_version().unwrap() >= (5, 0));
 }
 
 #[tokio::test]
@@ -30,6 +37,9 @@ async fn test_execute_request() {
 
     let action = client.execute_request("2 + 2".to_string(), handlers).await;
     action.await;
+    let state = client.kernel_state().borrow().clone();
+    assert_eq!(state.status, Some(KernelStatus::Idle));
+    assert!(state.total_busy_time() > Duration::ZERO);
     let counts = &handler.lock().await.counts;
     // All kernel types should give status busy -> status idle -> execute reply
     assert_eq!(counts["status"], 2);
@@ -44,3 +54,19 @@ async fn test_execute_request() {
     #[cfg(feature = "test_irkernel")]
     assert_eq!(counts["display_data"], 1);
 }
+
+#[tokio::test]
+async fn test_execute_request_stream() {
+    use tokio_stream::StreamExt;
+
+    let (_kernel, client) = start_kernel().await;
+
+    let mut stream = client.execute_request_stream("2 + 2".to_string()).await;
+    let mut msg_types = vec![];
+    while let Some(msg) = stream.next().await {
+        msg_types.push(msg.msg_type());
+    }
+    assert_eq!(msg_types.first().unwrap(), "status");
+    assert!(msg_types.contains(&"execute_reply".to_string()));
+    assert_eq!(stream.outcome().await, ActionStatus::Completed);
+}
@@ -0,0 +1,51 @@
+#![cfg(unix)]
+use std::time::Duration;
+
+use kernel_sidecar::jupyter::kernelspec::KernelSpec;
+use kernel_sidecar::kernels::{JupyterKernel, KernelLaunchOptions};
+
+#[test]
+fn test_launch_from_kernelspec_with_options() {
+    // Install a fake kernelspec that prints out its environment instead of running a Kernel
+    let jupyter_path = std::env::temp_dir().join(format!("ks-path-{}", uuid::Uuid::new_v4()));
+    let resource_dir = jupyter_path.join("kernels").join("Echo");
+    std::fs::create_dir_all(&resource_dir).unwrap();
+    let kernel_json = serde_json::json!({
+        "argv": ["sh", "-c", "echo $SPEC_VAR $OPTION_VAR $(pwd) $1 $2", "echo", "{resource_dir}"],
+        "display_name": "Echo",
+        "language": "shell",
+        "env": {"SPEC_VAR": "from-spec", "OPTION_VAR": "overridden"}
+    });
+    std::fs::write(resource_dir.join("kernel.json"), kernel_json.to_string()).unwrap();
+    std::env::set_var("JUPYTER_PATH", &jupyter_path);
+
+    let spec = KernelSpec::find("echo").expect("kernelspec not found");
+    assert_eq!(spec.name, "echo");
+    assert_eq!(spec.display_name, "Echo");
+    assert!(KernelSpec::list().iter().any(|s| s.name == "echo"));
+
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+    let options = KernelLaunchOptions::new()
+        .silent(true)
+        .cwd(&cwd)
+        .env("OPTION_VAR", "from-options")
+        .arg("extra-arg");
+    let kernel = JupyterKernel::from_spec(&spec, options);
+
+    let mut logs = vec![];
+    for _ in 0..50 {
+        logs = kernel.logs();
+        if !logs.is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let expected = format!(
+        "from-spec from-options {} {} extra-arg",
+        cwd.display(),
+        resource_dir.display()
+    );
+    assert_eq!(logs[0].line, expected);
+    drop(kernel);
+    std::fs::remove_dir_all(jupyter_path).unwrap();
+}
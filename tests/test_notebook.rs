@@ -14,3 +14,121 @@ fn test_notebook_structure() {
     let nb2: Notebook = serde_json::from_str(&serialized).unwrap();
     assert_eq!(nb, nb2);
 }
+
+#[test]
+fn test_update_display_data() {
+    use kernel_sidecar::jupyter::iopub_content::display_data::{DisplayData, UpdateDisplayData};
+    use kernel_sidecar::notebook::Output;
+    use serde_json::json;
+
+    let display = |text: &str, display_id: &str| -> Output {
+        let display: DisplayData = serde_json::from_value(json!({
+            "data": {"text/plain": text},
+            "metadata": {},
+            "transient": {"display_id": display_id},
+        }))
+        .unwrap();
+        Output::DisplayData(display)
+    };
+
+    let mut nb = Notebook::new();
+    let first = nb.add_code_cell("h = display('a', display_id='progress')");
+    let second = nb.add_code_cell("display('b', display_id='progress')");
+    let other = nb.add_code_cell("display('c', display_id='other')");
+    for (cell, output) in [
+        (&first, display("a", "progress")),
+        (&second, display("b", "progress")),
+        (&other, display("c", "other")),
+    ] {
+        nb.get_mut_cell(cell.id()).unwrap().add_output(output);
+    }
+    nb.track_display("progress", first.id());
+    nb.track_display("progress", second.id());
+    nb.track_display("other", other.id());
+
+    let update: UpdateDisplayData = serde_json::from_value(json!({
+        "data": {"text/plain": "done"},
+        "metadata": {},
+        "transient": {"display_id": "progress"},
+    }))
+    .unwrap();
+    assert_eq!(nb.update_display_data(&update), 2);
+
+    let text = |nb: &Notebook, id: &str| match nb.get_cell(id).unwrap() {
+        kernel_sidecar::notebook::Cell::Code(cell) => {
+            cell.outputs[0].as_display_data().unwrap().data["text/plain"].clone()
+        }
+        _ => unreachable!(),
+    };
+    assert_eq!(text(&nb, first.id()), "done");
+    assert_eq!(text(&nb, second.id()), "done");
+    assert_eq!(text(&nb, other.id()), "c");
+
+    // The index is rebuilt when loading a saved notebook
+    let path = std::env::temp_dir().join(format!("{}.ipynb", uuid::Uuid::new_v4()));
+    nb.save(path.to_str().unwrap());
+    let mut loaded = Notebook::from_file(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.update_display_data(&update), 2);
+
+    // and by plain deserializing, which doesn't compare the index either
+    let mut deserialized: Notebook = serde_json::from_str(&nb.dumps()).unwrap();
+    let mut tracked = deserialized.clone();
+    tracked.track_display("gone", other.id());
+    assert_eq!(deserialized, tracked);
+    assert_eq!(deserialized.update_display_data(&update), 2);
+}
+
+#[test]
+fn test_stream_coalescing() {
+    use kernel_sidecar::jupyter::iopub_content::stream::{Stream, StreamName};
+    use kernel_sidecar::notebook::{append_output, Output};
+
+    let stream = |name: StreamName, text: &str| {
+        let text = text.to_string();
+        Output::Stream(Stream { name, text }.into())
+    };
+    let text = |output: &Output| output.as_stream().unwrap().text.clone();
+
+    let mut outputs = vec![];
+    append_output(&mut outputs, stream(StreamName::Stdout, "foo\n"));
+    append_output(&mut outputs, stream(StreamName::Stdout, "bar\n"));
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(text(&outputs[0]), "foo\nbar\n");
+
+    // A different stream name starts a new output
+    append_output(&mut outputs, stream(StreamName::Stderr, "oops\n"));
+    append_output(&mut outputs, stream(StreamName::Stdout, "baz\n"));
+    assert_eq!(outputs.len(), 3);
+
+    // Progress bar style output only keeps its latest state, even across messages
+    let mut outputs = vec![];
+    for percent in [10, 50, 100] {
+        append_output(
+            &mut outputs,
+            stream(StreamName::Stderr, &format!("\r{percent:>3}%")),
+        );
+    }
+    append_output(&mut outputs, stream(StreamName::Stderr, "\ndone\n"));
+    assert_eq!(text(&outputs[0]), "100%\ndone\n");
+
+    // A carriage return overwrites, it doesn't erase
+    let mut outputs = vec![];
+    append_output(&mut outputs, stream(StreamName::Stdout, "abcdef\r"));
+    append_output(&mut outputs, stream(StreamName::Stdout, "XY"));
+    assert_eq!(text(&outputs[0]), "XYcdef");
+    // The cursor isn't saved with the text
+    let saved = serde_json::to_value(&outputs[0]).unwrap();
+    assert_eq!(saved["text"], "XYcdef");
+    assert!(saved.get("cursor").is_none());
+    append_output(&mut outputs, stream(StreamName::Stdout, "Z"));
+    assert_eq!(text(&outputs[0]), "XYZdef");
+    append_output(&mut outputs, stream(StreamName::Stdout, "\n"));
+    assert_eq!(text(&outputs[0]), "XYZdef\n");
+
+    // Backspaces, including one split from what it overwrites, and \r\n line endings
+    let mut outputs = vec![];
+    append_output(&mut outputs, stream(StreamName::Stdout, "ab\u{8}"));
+    append_output(&mut outputs, stream(StreamName::Stdout, "c\r\n"));
+    assert_eq!(text(&outputs[0]), "ac\n");
+}
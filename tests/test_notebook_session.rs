@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use kernel_sidecar::client::Client;
+use kernel_sidecar::jupyter::connection_file::ConnectionInfo;
+use kernel_sidecar::notebook::Notebook;
+use kernel_sidecar::session::{NotebookSession, RunCellError};
+use tokio::sync::Mutex;
+
+mod fake_kernel;
+use fake_kernel::bind_channels;
+
+#[tokio::test]
+async fn test_run_cell_errors() {
+    let connection_info = ConnectionInfo::new(None).unwrap();
+    let _channels = bind_channels(&connection_info).await;
+    let client = Client::new(connection_info).await;
+
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+    let markdown = nb.lock().await.add_markdown_cell("# Title");
+    let session = NotebookSession::new(nb, client);
+
+    // Neither of these sends anything to the (fake) Kernel
+    match session.run_cell("missing").await {
+        Err(RunCellError::CellNotFound(id)) => assert_eq!(id, "missing"),
+        other => panic!("Expected CellNotFound, got {:?}", other),
+    }
+    match session.run_cell(markdown.id()).await {
+        Err(RunCellError::NotCodeCell(id)) => assert_eq!(id, markdown.id()),
+        other => panic!("Expected NotCodeCell, got {:?}", other),
+    }
+    assert!(matches!(
+        session.run_above("missing").await,
+        Err(RunCellError::CellNotFound(_))
+    ));
+}
+
+#[cfg(feature = "test_ipython")]
+mod test_utils;
+
+#[cfg(feature = "test_ipython")]
+#[tokio::test]
+async fn test_run_all_above_below() {
+    use kernel_sidecar::execution_queue::ExecutionResult;
+    use kernel_sidecar::notebook::Cell;
+
+    let (_kernel, client) = test_utils::start_kernel().await;
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+    let (first, second, third) = {
+        let mut nb = nb.lock().await;
+        let first = nb.add_code_cell("x = 1");
+        nb.add_markdown_cell("Some notes");
+        let second = nb.add_code_cell("x += 1; x");
+        let third = nb.add_code_cell("print(x)");
+        (first, second, third)
+    };
+    let session = NotebookSession::new(nb.clone(), client);
+
+    let runs = session.run_all().await;
+    let ids: Vec<_> = runs.iter().map(|run| run.cell_id.as_str()).collect();
+    assert_eq!(ids, vec![first.id(), second.id(), third.id()]);
+    assert!(runs
+        .iter()
+        .all(|run| matches!(run.result, ExecutionResult::Ok { .. })));
+
+    let outputs = |nb: &Notebook, id: &str| match nb.get_cell(id).unwrap() {
+        Cell::Code(cell) => cell.outputs.clone(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        outputs(&*nb.lock().await, third.id())[0]
+            .as_stream()
+            .unwrap()
+            .text,
+        "2\n"
+    );
+
+    // Re-running replaces outputs instead of adding to them
+    let runs = session.run_below(second.id()).await.unwrap();
+    assert_eq!(runs.len(), 2);
+    let nb_guard = nb.lock().await;
+    assert_eq!(outputs(&nb_guard, second.id()).len(), 1);
+    assert_eq!(
+        outputs(&nb_guard, third.id())[0].as_stream().unwrap().text,
+        "3\n"
+    );
+    drop(nb_guard);
+
+    let runs = session.run_above(second.id()).await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].cell_id, first.id());
+
+    // Later cells are aborted after an error
+    nb.lock()
+        .await
+        .get_mut_cell(first.id())
+        .unwrap()
+        .set_source("1 / 0");
+    let runs = session.run_all().await;
+    assert!(matches!(runs[0].result, ExecutionResult::Error { .. }));
+    assert_eq!(runs[1].result, ExecutionResult::Aborted);
+    assert_eq!(runs[2].result, ExecutionResult::Aborted);
+}
@@ -118,3 +118,66 @@ async fn test_clear_output() {
         "qux\n"
     );
 }
+
+#[tokio::test]
+async fn test_update_display_data_across_cells() {
+    use kernel_sidecar::handlers::outputs::OutputHandler;
+    use kernel_sidecar::handlers::HandlerSet;
+    use kernel_sidecar::notebook::{Cell, Notebook};
+
+    let (_kernel, client) = start_kernel().await;
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+
+    let first = nb.lock().await.add_code_cell(indoc! {r#"
+    from IPython.display import display
+    h = display('working', display_id=True)
+    "#});
+    let second = nb.lock().await.add_code_cell("h.update('done')");
+    for cell in [&first, &second] {
+        let handlers = HandlerSet::new().with(OutputHandler::new(nb.clone(), cell.id()));
+        client
+            .execute_request(cell.get_source(), handlers)
+            .await
+            .await;
+    }
+
+    let nb = nb.lock().await;
+    match nb.get_cell(first.id()).unwrap() {
+        Cell::Code(cell) => {
+            let display = cell.outputs[0].as_display_data().unwrap();
+            assert_eq!(display.data["text/plain"], "'done'");
+        }
+        _ => unreachable!(),
+    }
+    // The update lands in the cell that made the display, not the one that sent it
+    match nb.get_cell(second.id()).unwrap() {
+        Cell::Code(cell) => assert!(cell.outputs.is_empty()),
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn test_execution_count() {
+    use kernel_sidecar::handlers::outputs::OutputHandler;
+    use kernel_sidecar::handlers::HandlerSet;
+    use kernel_sidecar::notebook::{Cell, Notebook};
+
+    let (_kernel, client) = start_kernel().await;
+    let nb = Arc::new(Mutex::new(Notebook::new()));
+    let first = nb.lock().await.add_code_cell("x = 1");
+    let second = nb.lock().await.add_code_cell("x");
+    for cell in [&first, &second] {
+        let handlers = HandlerSet::new().with(OutputHandler::new(nb.clone(), cell.id()));
+        client
+            .execute_request(cell.get_source(), handlers)
+            .await
+            .await;
+    }
+
+    let nb = nb.lock().await;
+    let count = |id: &str| match nb.get_cell(id).unwrap() {
+        Cell::Code(cell) => cell.execution_count.unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(count(second.id()), count(first.id()) + 1);
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use kernel_sidecar::jupyter::iopub_content::status::{KernelStatus, Status};
+
+#[test]
+fn test_status_states() {
+    for (state, expected) in [
+        ("busy", KernelStatus::Busy),
+        ("idle", KernelStatus::Idle),
+        ("starting", KernelStatus::Starting),
+        ("restarting", KernelStatus::Restarting),
+        ("dead", KernelStatus::Dead),
+        (
+            "hibernating",
+            KernelStatus::Unknown("hibernating".to_string()),
+        ),
+    ] {
+        let content = serde_json::json!({ "execution_state": state });
+        let status: Status = Bytes::from(content.to_string()).into();
+        assert_eq!(status.execution_state, expected);
+        // And back out the same way it came in
+        let serialized = serde_json::to_value(&status).unwrap();
+        assert_eq!(serialized, content);
+    }
+}